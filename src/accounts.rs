@@ -8,6 +8,11 @@ use rugflo::Float;
 use money::Money;
 use errors::*;
 use expression::*;
+use strategy::Strategy;
+use contributions::*;
+use gains::*;
+use accrual::*;
+use limits::*;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -26,6 +31,12 @@ pub enum Account {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SimpleAccount {
     pub amount: Money,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contribution_limit: Option<ContributionLimit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub book_value: Option<Money>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance_limit: Option<BalanceLimit>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -47,6 +58,17 @@ fn eval(expr: &Expr, root: &Accounts) -> Result<Money> {
     }
 }
 
+impl SimpleAccount {
+    pub fn new(amount: Money) -> SimpleAccount {
+        SimpleAccount {
+            amount: amount,
+            contribution_limit: None,
+            book_value: None,
+            balance_limit: None,
+        }
+    }
+}
+
 impl Account {
     pub fn amount(&self) -> Money {
         match *self {
@@ -91,6 +113,38 @@ impl Accounts {
         }
     }
 
+    pub fn get_mut(&mut self, path: &str) -> Result<&mut Accounts> {
+        match *self {
+            Accounts::Tree(ref mut m) => {
+                if let Some(index) = path.find(':') {
+                    let (account, sub_account) = path.split_at(index);
+                    match m.get_mut(account) {
+                        Some(a) => a.get_mut(&sub_account[1..]),
+                        None => Err(ErrorKind::InvalidAccountName(String::from(path)).into()),
+                    }
+                } else {
+                    m.get_mut(path)
+                        .ok_or_else(|| ErrorKind::InvalidAccountName(String::from(path)).into())
+                }
+            }
+            ref mut a => {
+                if path.is_empty() {
+                    Ok(a)
+                } else {
+                    Err(ErrorKind::InvalidAccountName(String::from(path)).into())
+                }
+            }
+        }
+    }
+
+    // the balance of a single account, a derived account, or a whole tree of accounts
+    pub fn balance(&self, path: &str) -> Result<Money> {
+        match *self.get(path)? {
+            Accounts::Leaf(Account::Derived(ref d)) => eval(&d.expression, self),
+            ref a => Ok(a.sum()),
+        }
+    }
+
     pub fn sum(&self) -> Money {
         let result = match *self {
             Accounts::Tree(ref m) => {
@@ -191,6 +245,21 @@ impl Accounts {
         }
     }
 
+    // replaces the account at the path if there is one, otherwise creates it
+    pub fn set_account(&mut self, path: String, account: Account) -> Result<()> {
+        if let Ok(existing) = self.get_mut(&path) {
+            *existing = Accounts::Leaf(account);
+            return Ok(());
+        }
+        self.create_account(path, account)
+    }
+
+    pub fn merge(&mut self, other: Accounts) -> Result<()> {
+        other.fold_with_path(Ok(()), |result, path, account| {
+            result.and_then(|_| self.set_account(String::from(path), account))
+        })
+    }
+
     pub fn deposit(&mut self, path: String, amount: Money) -> Result<()> {
         match *self {
             Accounts::Tree(ref mut m) => {
@@ -236,21 +305,25 @@ impl Accounts {
         Ok(())
     }
 
-    pub fn apply(&mut self, transaction: Transaction) -> Result<()> {
+    pub fn apply(&mut self, transaction: Transaction) -> Result<Money> {
         if self.get(&transaction.from).is_err() {
             self.create_account(transaction.from.clone(),
-                                Account::Simple(SimpleAccount { amount: Money::from(0) }))?;
+                                Account::Simple(SimpleAccount::new(Money::from(0))))?;
         }
         if self.get(&transaction.to).is_err() {
             self.create_account(transaction.to.clone(),
-                                Account::Simple(SimpleAccount { amount: Money::from(0) }))?;
+                                Account::Simple(SimpleAccount::new(Money::from(0))))?;
         }
 
         trace!("apply: {}", transaction);
         let eval_from_amount = transaction.eval_from_amount(self)?;
         self.withdraw(transaction.from, eval_from_amount.clone())?;
-        self.deposit(transaction.to, eval_from_amount)?;
-        Ok(())
+        self.deposit(transaction.to, eval_from_amount.clone())?;
+        Ok(eval_from_amount)
+    }
+
+    pub fn evaluate(&self, expr: &Expr) -> Result<Money> {
+        eval(expr, self)
     }
 
     pub fn eval(&self) -> Result<HashMap<String, Money>> {
@@ -273,6 +346,8 @@ pub struct Transaction {
     pub from: String,
     pub to: String,
     pub date: NaiveDate,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl Transaction {
@@ -282,9 +357,15 @@ impl Transaction {
             from: from,
             to: to,
             date: date,
+            tags: Vec::new(),
         }
     }
 
+    pub fn with_tags(mut self, tags: Vec<String>) -> Transaction {
+        self.tags = tags;
+        self
+    }
+
     pub fn eval_from_amount(&self, accounts: &Accounts) -> Result<Money> {
         self.amount.eval(accounts, &self.from)
     }
@@ -365,6 +446,11 @@ pub struct CompoundedInterest {
     pub date: NaiveDate,
     pub amount: f64,
     pub account: String,
+    // interest that accrues daily is paid on the average daily balance at the yearly rate, with
+    // the name of the rule it accrues for
+    pub accrual: Option<(String, Accrual, f64)>,
+    // interest is paid into the account, but fees are paid out of it to here
+    pub paid_to: Option<String>,
 }
 
 impl CompoundedInterest {
@@ -373,8 +459,20 @@ impl CompoundedInterest {
             date: date,
             amount: amount,
             account: account,
+            accrual: None,
+            paid_to: None,
         }
     }
+
+    pub fn with_accrual(mut self, rule: String, accrual: Accrual, rate: f64) -> CompoundedInterest {
+        self.accrual = Some((rule, accrual, rate));
+        self
+    }
+
+    pub fn paid_to(mut self, account: String) -> CompoundedInterest {
+        self.paid_to = Some(account);
+        self
+    }
 }
 
 impl fmt::Display for CompoundedInterest {
@@ -401,14 +499,46 @@ impl Ord for CompoundedInterest {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Warning {
+    pub date: NaiveDate,
+    pub account: String,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(date: NaiveDate, account: String, message: String) -> Warning {
+        Warning {
+            date: date,
+            account: account,
+            message: message,
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} [{}] {}", self.date, self.account, self.message)
+    }
+}
+
 pub struct History<T: Iterator<Item = Transaction>,
                    C: Iterator<Item = CompoundedInterest>,
                    D: Iterator<Item = NaiveDate>>
 {
     transactions: Peekable<T>,
     interest: Peekable<C>,
+    strategies: Vec<Box<Strategy>>,
     dates: D,
     state: (NaiveDate, Accounts),
+    room: ContributionRoom,
+    warnings: Vec<Warning>,
+    gains: Vec<RealizedGain>,
+    accruals: Accruals,
+    limit_hits: Vec<LimitHit>,
+    failure: Option<Error>,
+    // every transaction that was applied and how much it moved, if anyone asked for it
+    journal: Option<Vec<(Transaction, Money)>>,
 }
 
 impl<T, C, D> History<T, C, D>
@@ -419,15 +549,168 @@ impl<T, C, D> History<T, C, D>
     pub fn new(state: (NaiveDate, Accounts),
                transactions: T,
                interest: C,
+               strategies: Vec<Box<Strategy>>,
                dates: D)
                -> History<T, C, D> {
+        let room = ContributionRoom::new(&state.1, state.0.year());
+        let accruals = Accruals::new(Vec::new(), state.0);
         History {
             transactions: transactions.peekable(),
             interest: interest.peekable(),
+            strategies: strategies,
             dates: dates,
             state: state,
+            room: room,
+            warnings: Vec::new(),
+            gains: Vec::new(),
+            accruals: accruals,
+            limit_hits: Vec::new(),
+            failure: None,
+            journal: None,
+        }
+    }
+
+    pub fn with_accruals(mut self, accounts: Vec<(String, String, Accrual)>) -> History<T, C, D> {
+        self.accruals = Accruals::new(accounts, self.state.0);
+        self
+    }
+
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    pub fn contribution_room(&self) -> &ContributionRoom {
+        &self.room
+    }
+
+    pub fn realized_gains(&self) -> &[RealizedGain] {
+        &self.gains
+    }
+
+    pub fn with_journal(mut self) -> History<T, C, D> {
+        self.journal = Some(Vec::new());
+        self
+    }
+
+    pub fn journal(&self) -> &[(Transaction, Money)] {
+        match self.journal {
+            Some(ref journal) => journal,
+            None => &[],
+        }
+    }
+
+    pub fn limit_hits(&self) -> &[LimitHit] {
+        &self.limit_hits
+    }
+
+    // the history stops early when a limit fails the simulation, or a rule or transaction can't
+    // run
+    pub fn take_failure(&mut self) -> Result<()> {
+        match self.failure.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn apply(&mut self, transaction: Transaction) {
+        if self.failure.is_some() {
+            return;
+        }
+        // balance limits go first, so that contribution room is only used up by what they
+        // let through
+        let enforced = match enforce(transaction, &self.state.1, &mut self.limit_hits) {
+            Ok(transactions) => transactions,
+            Err(e) => {
+                self.failure = Some(e);
+                return;
+            }
+        };
+        let mut limited = Vec::new();
+        for transaction in enforced {
+            match self.room.limit(transaction, &self.state.1, &mut self.warnings) {
+                Ok(transactions) => limited.extend(transactions),
+                Err(e) => {
+                    self.failure = Some(e);
+                    return;
+                }
+            }
+        }
+        for transaction in limited {
+            self.accruals.touch(transaction.date, &self.state.1);
+            match track_cost_basis(&transaction, &mut self.state.1) {
+                Ok(Some(gain)) => {
+                    for strategy in &mut self.strategies {
+                        strategy.realize(&gain);
+                    }
+                    self.gains.push(gain);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    self.failure = Some(e);
+                    return;
+                }
+            }
+
+            // like money going into a derived account
+            let amount = match self.state.1.apply(transaction.clone()) {
+                Ok(amount) => amount,
+                Err(e) => {
+                    self.failure = Some(e);
+                    return;
+                }
+            };
+            for strategy in &mut self.strategies {
+                strategy.observe(&transaction, &amount);
+            }
+            if let Some(ref mut journal) = self.journal {
+                journal.push((transaction, amount));
+            }
         }
     }
+
+    fn next_strategy(&self) -> Option<(usize, NaiveDate)> {
+        self.strategies
+            .iter()
+            .enumerate()
+            .filter_map(|(index, strategy)| strategy.next_date().map(|date| (index, date)))
+            .min_by_key(|&(_, date)| date)
+    }
+
+    fn compound(&mut self, interest: CompoundedInterest) {
+        if self.failure.is_some() {
+            return;
+        }
+        let evaluated = match interest.accrual {
+            Some((ref rule, _, rate)) => {
+                self.accruals.pay(rule, &interest.account, rate, interest.date, &self.state.1)
+            }
+            None => {
+                let balances = match self.state.1.eval() {
+                    Ok(balances) => balances,
+                    Err(e) => {
+                        self.failure = Some(e);
+                        return;
+                    }
+                };
+                match balances.get(&interest.account) {
+                    Some(amount) => amount.clone().mul_percent(Float::from((interest.amount, 64))),
+                    None => Money::from(0),
+                }
+            }
+        };
+        let transaction = match interest.paid_to {
+            Some(paid_to) => {
+                Transaction::new(Amount::Money(evaluated), interest.account, paid_to, interest.date)
+            }
+            None => {
+                Transaction::new(Amount::Money(evaluated),
+                                 format!("equity:interest:{}", interest.account.clone()),
+                                 interest.account,
+                                 interest.date)
+            }
+        };
+        self.apply(transaction);
+    }
 }
 
 // this assumes that users have validated the transactions first :)
@@ -444,46 +727,52 @@ impl<T, C, D> Iterator for History<T, C, D>
 
                 self.state.0 = next_date;
 
+                // everything up to the next date happens in order, so that strategies and
+                // interest see the balances left behind by whatever happened before them
                 loop {
-                    let consume = if let Some(transaction) = self.transactions.peek() {
-                        transaction.date <= next_date
-                    } else {
-                        false
-                    };
-                    if consume {
-                        self.state.1.apply(self.transactions.next().unwrap()).unwrap();
-                    } else {
+                    let transaction_date = self.transactions.peek().map(|t| t.date);
+                    let interest_date = self.interest.peek().map(|i| i.date);
+                    let strategy = self.next_strategy();
+                    let strategy_date = strategy.map(|(_, date)| date);
+
+                    if self.failure.is_some() {
                         break;
                     }
-                }
 
-                loop {
-                    let consume = if let Some(interest) = self.interest.peek() {
-                        interest.date <= next_date
-                    } else {
-                        false
+                    let date = match [transaction_date, strategy_date, interest_date]
+                        .iter()
+                        .filter_map(|date| *date)
+                        .min() {
+                        Some(date) if date <= next_date => date,
+                        _ => break,
                     };
-                    if consume {
-                        let interest = self.interest.next().unwrap();
-                        let evaluated = match self.state.1.eval().unwrap().get(&interest.account) {
-                            Some(amount) => {
-                                amount.clone().mul_percent(Float::from((interest.amount, 64)))
+
+                    if transaction_date == Some(date) {
+                        let transaction = self.transactions.next().unwrap();
+                        self.apply(transaction);
+                    } else if strategy_date == Some(date) {
+                        let index = strategy.unwrap().0;
+                        let transactions = match self.strategies[index].run(&self.state.1) {
+                            Ok(transactions) => transactions,
+                            Err(e) => {
+                                self.failure = Some(e);
+                                break;
                             }
-                            None => Money::from(0),
                         };
-                        self.state
-                            .1
-                            .apply(Transaction::new(Amount::Money(evaluated),
-                                                    format!("equity:interest:{}",
-                                                            interest.account.clone()),
-                                                    interest.account,
-                                                    interest.date))
-                            .unwrap();
+                        for transaction in transactions {
+                            self.apply(transaction);
+                        }
+                        let warnings = self.strategies[index].warnings();
+                        self.warnings.extend(warnings);
                     } else {
-                        break;
+                        let interest = self.interest.next().unwrap();
+                        self.compound(interest);
                     }
                 }
 
+                if self.failure.is_some() {
+                    return None;
+                }
                 Some(self.state.clone())
             }
             None => None,
@@ -493,5 +782,64 @@ impl<T, C, D> Iterator for History<T, C, D>
 
 #[cfg(test)]
 mod tests {
-    // TODO
+    use super::*;
+
+    fn simple(amount: i32) -> Account {
+        Account::Simple(SimpleAccount::new(Money::from(amount)))
+    }
+
+    fn accounts() -> Accounts {
+        let mut accounts = Accounts::root();
+        accounts.create_account(String::from("assets:savings"), simple(1000)).unwrap();
+        accounts.create_account(String::from("assets:chequing"), simple(200)).unwrap();
+        accounts
+    }
+
+    #[test]
+    fn test_set_account_replaces_or_creates() {
+        let mut accounts = accounts();
+        accounts.set_account(String::from("assets:savings"), simple(500)).unwrap();
+        accounts.set_account(String::from("liabilities:visa"), simple(300)).unwrap();
+
+        let balances = accounts.eval().unwrap();
+        assert_eq!(balances["assets:savings"], Money::from(500));
+        assert_eq!(balances["assets:chequing"], Money::from(200));
+        assert_eq!(balances["liabilities:visa"], Money::from(300));
+    }
+
+    #[test]
+    fn test_merge_overlays_accounts() {
+        let mut accounts = accounts();
+        let mut other = Accounts::root();
+        other.create_account(String::from("assets:savings"), simple(2000)).unwrap();
+        other.create_account(String::from("assets:tfsa"), simple(50)).unwrap();
+        accounts.merge(other).unwrap();
+
+        let balances = accounts.eval().unwrap();
+        assert_eq!(balances.len(), 3);
+        assert_eq!(balances["assets:savings"], Money::from(2000));
+        assert_eq!(balances["assets:chequing"], Money::from(200));
+        assert_eq!(balances["assets:tfsa"], Money::from(50));
+    }
+
+    #[test]
+    fn test_money_into_a_derived_account_fails_the_history() {
+        let plan: ::plan::Plan = ::serde_yaml::from_str("start_date: 2017-01-01\n\
+                                                         accounts:\n  \
+                                                           assets:\n    \
+                                                             chequing:\n      \
+                                                               amount: 1000\n  \
+                                                           net:\n    \
+                                                             expression: assets\n\
+                                                         rules:\n  \
+                                                           split:\n    \
+                                                             amount: 100\n    \
+                                                             from: assets:chequing\n    \
+                                                             frequency: Monthly\n    \
+                                                             targets:\n      \
+                                                               - account: net")
+            .unwrap();
+        let dates = ::plan::DateStream::monthly(Some(NaiveDate::from_ymd(2017, 1, 1))).take(3);
+        assert!(plan.moments(dates).is_err());
+    }
 }
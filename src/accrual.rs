@@ -0,0 +1,165 @@
+use std::cmp;
+use std::collections::HashMap;
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::Accounts;
+
+// how interest accrues every day between payments
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Accrual {
+    // the actual number of days, with 365 days in a year
+    Actual365,
+    // every month has 30 days, with 360 days in a year
+    Thirty360,
+}
+
+impl Accrual {
+    pub fn days_per_year(&self) -> f64 {
+        match *self {
+            Accrual::Actual365 => 365.0,
+            Accrual::Thirty360 => 360.0,
+        }
+    }
+
+    pub fn days_between(&self, from: NaiveDate, to: NaiveDate) -> i64 {
+        match *self {
+            Accrual::Actual365 => (to - from).num_days(),
+            Accrual::Thirty360 => {
+                let from_day = cmp::min(from.day(), 30) as i64;
+                let to_day = if to.day() == 31 && from_day == 30 {
+                    30
+                } else {
+                    to.day() as i64
+                };
+                360 * (to.year() - from.year()) as i64 +
+                30 * (to.month() as i64 - from.month() as i64) + (to_day - from_day)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Accrued {
+    accrual: Accrual,
+    since: NaiveDate,
+    // the balance multiplied by the number of days it was there for
+    balance_days: f64,
+}
+
+// keeps a running sum of the daily balances of the accounts that accrue interest, so that
+// interest can be paid on the average daily balance. every rule keeps its own sum, so that one
+// paying doesn't leave nothing for another on the same account.
+#[derive(Clone, Debug)]
+pub struct Accruals {
+    accounts: HashMap<(String, String), Accrued>,
+}
+
+impl Accruals {
+    pub fn new(accounts: Vec<(String, String, Accrual)>, date: NaiveDate) -> Accruals {
+        let mut result = HashMap::new();
+        for (rule, account, accrual) in accounts {
+            result.entry((rule, account)).or_insert(Accrued {
+                accrual: accrual,
+                since: date,
+                balance_days: 0.0,
+            });
+        }
+        Accruals { accounts: result }
+    }
+
+    // called before the balances change on a date, the old balances were there until then
+    pub fn touch(&mut self, date: NaiveDate, accounts: &Accounts) {
+        for (&(_, ref account), accrued) in &mut self.accounts {
+            if date <= accrued.since {
+                continue;
+            }
+            let balance = accounts.balance(account).unwrap_or_else(|_| Money::zero()).to_f64();
+            let days = accrued.accrual.days_between(accrued.since, date);
+            accrued.balance_days += balance * days as f64;
+            accrued.since = date;
+        }
+    }
+
+    // the interest accrued for a rule since its last payment, at a yearly rate
+    pub fn pay(&mut self,
+               rule: &str,
+               account: &str,
+               rate: f64,
+               date: NaiveDate,
+               accounts: &Accounts)
+               -> Money {
+        self.touch(date, accounts);
+        match self.accounts.get_mut(&(String::from(rule), String::from(account))) {
+            Some(accrued) => {
+                let interest = accrued.balance_days * rate / accrued.accrual.days_per_year();
+                accrued.balance_days = 0.0;
+                Money::from(interest)
+            }
+            None => Money::zero(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use accounts::*;
+
+    #[test]
+    fn test_day_counts() {
+        let from = NaiveDate::from_ymd(2017, 1, 31);
+        let to = NaiveDate::from_ymd(2017, 3, 1);
+        assert_eq!(Accrual::Actual365.days_between(from, to), 29);
+        assert_eq!(Accrual::Thirty360.days_between(from, to), 31);
+    }
+
+    fn accounts(balance: i32) -> Accounts {
+        let mut accounts = Accounts::root();
+        accounts.create_account(String::from("assets:savings"),
+                                Account::Simple(SimpleAccount::new(Money::from(balance))))
+            .unwrap();
+        accounts
+    }
+
+    #[test]
+    fn test_pays_on_the_average_daily_balance() {
+        let start = NaiveDate::from_ymd(2017, 1, 1);
+        let accruals = vec![(String::from("interest"), String::from("assets:savings"),
+                             Accrual::Actual365)];
+        let mut accruals = Accruals::new(accruals, start);
+
+        // 1000 for 73 days, then 2000 for 73 days
+        accruals.touch(NaiveDate::from_ymd(2017, 3, 15), &accounts(1000));
+        let paid = accruals.pay("interest",
+                                "assets:savings",
+                                0.05,
+                                NaiveDate::from_ymd(2017, 5, 27),
+                                &accounts(2000));
+        assert_eq!((paid.to_f64() * 100.0).round(), 3000.0);
+
+        // the next payment only has what accrued since this one
+        let paid = accruals.pay("interest",
+                                "assets:savings",
+                                0.05,
+                                NaiveDate::from_ymd(2017, 8, 8),
+                                &accounts(2000));
+        assert_eq!((paid.to_f64() * 100.0).round(), 2000.0);
+    }
+
+    #[test]
+    fn test_rules_on_one_account_accrue_separately() {
+        let start = NaiveDate::from_ymd(2017, 1, 1);
+        let accruals = vec![(String::from("base"), String::from("assets:savings"),
+                             Accrual::Actual365),
+                            (String::from("bonus"), String::from("assets:savings"),
+                             Accrual::Actual365)];
+        let mut accruals = Accruals::new(accruals, start);
+        let date = NaiveDate::from_ymd(2017, 3, 15);
+
+        let base = accruals.pay("base", "assets:savings", 0.05, date, &accounts(1000));
+        let bonus = accruals.pay("bonus", "assets:savings", 0.05, date, &accounts(1000));
+        assert_eq!(base, bonus);
+        assert!(bonus > Money::zero());
+    }
+}
@@ -0,0 +1,292 @@
+use std::fmt::Write;
+use std::str::FromStr;
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+use output::escape;
+
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 400.0;
+// room for the labels on the axes, and the legend on the right
+const LEFT: f64 = 90.0;
+const RIGHT: f64 = 180.0;
+const TOP: f64 = 20.0;
+const BOTTOM: f64 = 40.0;
+const COLOURS: [&'static str; 8] = ["#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f",
+                                    "#edc948", "#b07aa1", "#9c755f"];
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+pub const STYLES: [&'static str; 2] = ["line", "stacked"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChartStyle {
+    Line,
+    // every account on top of the one before it, so the top is the total
+    Stacked,
+}
+
+impl FromStr for ChartStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ChartStyle> {
+        match s {
+            "line" => Ok(ChartStyle::Line),
+            "stacked" => Ok(ChartStyle::Stacked),
+            _ => Err(ErrorKind::UnknownStyle(String::from(s)).into()),
+        }
+    }
+}
+
+// the balance of an account (or everything under it) over time
+#[derive(Clone, Debug)]
+pub struct Series {
+    pub name: String,
+    pub points: Vec<(NaiveDate, f64)>,
+}
+
+// a series for every account selected, or every account when none are. selecting an account
+// with sub accounts charts their total, and an account is at zero before it is first used.
+pub fn series(moments: &[(NaiveDate, Accounts)], selected: &[String]) -> Result<Vec<Series>> {
+    let names = if selected.is_empty() {
+        match moments.first() {
+            Some(&(_, ref accounts)) => accounts.get_account_names(),
+            None => Vec::new(),
+        }
+    } else {
+        selected.to_vec()
+    };
+
+    let mut series = Vec::new();
+    for name in names {
+        if !moments.iter().any(|&(_, ref accounts)| accounts.get(&name).is_ok()) {
+            bail!(ErrorKind::UnknownAccount(name));
+        }
+        let mut points = Vec::new();
+        for &(date, ref accounts) in moments {
+            let balance = accounts.balance(&name).unwrap_or_else(|_| Money::zero());
+            points.push((date, balance.to_f64()));
+        }
+        series.push(Series {
+            name: name,
+            points: points,
+        });
+    }
+    Ok(series)
+}
+
+pub fn sparkline(values: &[f64]) -> String {
+    let low = values.iter().cloned().fold(::std::f64::INFINITY, f64::min);
+    let high = values.iter().cloned().fold(::std::f64::NEG_INFINITY, f64::max);
+    values.iter()
+        .map(|value| {
+            let index = if high > low {
+                ((value - low) / (high - low) * (BARS.len() - 1) as f64).round() as usize
+            } else {
+                0
+            };
+            BARS[index]
+        })
+        .collect()
+}
+
+// a line for every series in the terminal, with where it starts and ends
+pub fn sparklines(series: &[Series]) -> Result<String> {
+    let width = series.iter().map(|s| s.name.chars().count()).max().unwrap_or(0);
+    let mut out = String::new();
+    for s in series {
+        let values: Vec<f64> = s.points.iter().map(|&(_, value)| value).collect();
+        let first = values.first().cloned().unwrap_or(0.0);
+        let last = values.last().cloned().unwrap_or(0.0);
+        writeln!(out,
+                 "{:width$}  {}  {} -> {}",
+                 s.name,
+                 sparkline(&values),
+                 Money::from(first),
+                 Money::from(last),
+                 width = width)?;
+    }
+    Ok(out)
+}
+
+// whole dollars, shortened when they get big
+fn label(value: f64) -> String {
+    let size = value.abs();
+    if size >= 1000000.0 {
+        format!("${:.1}M", value / 1000000.0)
+    } else if size >= 10000.0 {
+        format!("${:.0}k", value / 1000.0)
+    } else {
+        format!("${:.0}", value)
+    }
+}
+
+fn path(points: &[(f64, f64)]) -> String {
+    points.iter()
+        .map(|&(x, y)| format!("{:.1},{:.1}", x, y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn svg(series: &[Series], style: ChartStyle) -> Result<String> {
+    let dates: Vec<NaiveDate> = series.first()
+        .map(|s| s.points.iter().map(|&(date, _)| date).collect())
+        .unwrap_or_else(Vec::new);
+
+    // stacked series are drawn from the running total of the ones before them
+    let mut lines: Vec<(Vec<f64>, Vec<f64>)> = Vec::new();
+    let mut base = vec![0.0; dates.len()];
+    for s in series {
+        let values: Vec<f64> = s.points.iter().map(|&(_, value)| value).collect();
+        match style {
+            ChartStyle::Line => lines.push((vec![0.0; dates.len()], values)),
+            ChartStyle::Stacked => {
+                let top: Vec<f64> = base.iter().zip(values.iter()).map(|(b, v)| b + v).collect();
+                lines.push((base, top.clone()));
+                base = top;
+            }
+        }
+    }
+
+    let all = lines.iter().flat_map(|&(ref bottom, ref top)| bottom.iter().chain(top.iter()));
+    let (low, high) = all.fold((0.0f64, 0.0f64), |(low, high), v| (low.min(*v), high.max(*v)));
+    let high = if high > low { high } else { low + 1.0 };
+
+    let plot_width = WIDTH - LEFT - RIGHT;
+    let plot_height = HEIGHT - TOP - BOTTOM;
+    let first = dates.first().map_or(0, |date| date.num_days_from_ce());
+    let days = dates.last().map_or(1, |date| (date.num_days_from_ce() - first).max(1));
+    let x = |date: NaiveDate| {
+        LEFT + (date.num_days_from_ce() - first) as f64 / days as f64 * plot_width
+    };
+    let y = |value: f64| TOP + (high - value) / (high - low) * plot_height;
+
+    let mut out = String::new();
+    writeln!(out,
+             "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+              viewBox=\"0 0 {} {}\" font-family=\"sans-serif\" font-size=\"12\">",
+             WIDTH,
+             HEIGHT,
+             WIDTH,
+             HEIGHT)?;
+    writeln!(out, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>")?;
+
+    // grid lines and the value axis
+    for step in 0..5 {
+        let value = low + (high - low) * step as f64 / 4.0;
+        writeln!(out,
+                 "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#ddd\"/>\n\
+                  <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+                 LEFT,
+                 y(value),
+                 LEFT + plot_width,
+                 y(value),
+                 LEFT - 6.0,
+                 y(value) + 4.0,
+                 label(value))?;
+    }
+
+    // a label for at most ten of the dates
+    let every = ((dates.len() + 9) / 10).max(1);
+    for (_, date) in dates.iter().enumerate().filter(|&(index, _)| index % every == 0) {
+        writeln!(out,
+                 "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+                 x(*date),
+                 HEIGHT - BOTTOM + 18.0,
+                 date.year())?;
+    }
+
+    for (index, (s, &(ref bottom, ref top))) in series.iter().zip(lines.iter()).enumerate() {
+        let colour = COLOURS[index % COLOURS.len()];
+        let upper: Vec<(f64, f64)> =
+            dates.iter().zip(top.iter()).map(|(d, v)| (x(*d), y(*v))).collect();
+        match style {
+            ChartStyle::Line => {
+                writeln!(out,
+                         "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" \
+                          stroke-width=\"2\"/>",
+                         path(&upper),
+                         colour)?;
+            }
+            ChartStyle::Stacked => {
+                let mut area = upper.clone();
+                area.extend(dates.iter().zip(bottom.iter()).rev().map(|(d, v)| (x(*d), y(*v))));
+                writeln!(out,
+                         "<polygon points=\"{}\" fill=\"{}\" fill-opacity=\"0.8\" \
+                          stroke=\"{}\"/>",
+                         path(&area),
+                         colour,
+                         colour)?;
+            }
+        }
+
+        let legend = TOP + 10.0 + index as f64 * 18.0;
+        writeln!(out,
+                 "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"12\" height=\"12\" fill=\"{}\"/>\n\
+                  <text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+                 WIDTH - RIGHT + 16.0,
+                 legend - 10.0,
+                 colour,
+                 WIDTH - RIGHT + 34.0,
+                 legend,
+                 escape(&s.name))?;
+    }
+
+    writeln!(out, "</svg>")?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[0.0, 50.0, 100.0]), "▁▅█");
+        assert_eq!(sparkline(&[5.0, 5.0]), "▁▁");
+    }
+
+    fn moments() -> Vec<(NaiveDate, Accounts)> {
+        let mut moments = Vec::new();
+        for (year, savings, stocks) in vec![(2017, 1000, 0), (2018, 2000, 500)] {
+            let mut accounts = Accounts::root();
+            accounts.set_account(String::from("assets:savings"),
+                                 Account::Simple(SimpleAccount::new(Money::from(savings))))
+                .unwrap();
+            accounts.set_account(String::from("assets:stocks"),
+                                 Account::Simple(SimpleAccount::new(Money::from(stocks))))
+                .unwrap();
+            moments.push((NaiveDate::from_ymd(year, 1, 1), accounts));
+        }
+        moments
+    }
+
+    #[test]
+    fn test_series() {
+        let moments = moments();
+        let totals = series(&moments, &[String::from("assets")]).unwrap();
+        assert_eq!(totals[0].points[1], (NaiveDate::from_ymd(2018, 1, 1), 2500.0));
+        assert!(series(&moments, &[String::from("liabilities")]).is_err());
+        assert!("area".parse::<ChartStyle>().is_err());
+    }
+
+    #[test]
+    fn test_svg() {
+        let selected = [String::from("assets:savings"), String::from("assets:stocks")];
+        let series = series(&moments(), &selected).unwrap();
+
+        let lines = svg(&series, ChartStyle::Line).unwrap();
+        assert!(lines.starts_with("<svg"));
+        assert!(lines.trim_end().ends_with("</svg>"));
+        assert_eq!(lines.matches("<polyline").count(), 2);
+        assert!(lines.contains(">assets:stocks</text>"));
+        // the top of the value axis is the highest balance
+        assert!(lines.contains(">$2000</text>"));
+
+        // stacked, the top is the total of both
+        let stacked = svg(&series, ChartStyle::Stacked).unwrap();
+        assert_eq!(stacked.matches("<polygon").count(), 2);
+        assert!(stacked.contains(">$2500</text>"));
+    }
+}
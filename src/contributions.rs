@@ -0,0 +1,268 @@
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+
+// registered accounts (rrsp, tfsa, 401k, ira) can only take so much money every year
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContributionLimit {
+    pub limits: Vec<AnnualLimit>,
+    #[serde(default)]
+    pub carry_forward: bool,
+    pub room: Option<Money>,
+    pub excess: Option<Excess>,
+}
+
+// a limit applies from its year until the next limit in the schedule
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AnnualLimit {
+    pub year: i32,
+    pub amount: Money,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Excess {
+    Clip,
+    Redirect(String),
+    Flag,
+}
+
+impl ContributionLimit {
+    fn limit(&self, year: i32) -> Money {
+        self.limits
+            .iter()
+            .filter(|limit| limit.year <= year)
+            .max_by_key(|limit| limit.year)
+            .map(|limit| limit.amount.clone())
+            .unwrap_or_else(Money::zero)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RoomYear {
+    pub account: String,
+    pub year: i32,
+    pub available: Money,
+    pub contributed: Money,
+    pub unused: Money,
+}
+
+#[derive(Clone, Debug)]
+struct Room {
+    account: String,
+    limit: ContributionLimit,
+    year: i32,
+    available: Money,
+    contributed: Money,
+    years: Vec<RoomYear>,
+}
+
+impl Room {
+    fn new(account: String, limit: ContributionLimit, year: i32) -> Room {
+        Room {
+            account: account,
+            available: limit.room.clone().unwrap_or_else(Money::zero) + limit.limit(year),
+            limit: limit,
+            year: year,
+            contributed: Money::zero(),
+            years: Vec::new(),
+        }
+    }
+
+    fn remaining(&self) -> Money {
+        self.available.clone() - self.contributed.clone()
+    }
+
+    // close off every year before this one, carrying unused room forward if we can
+    fn roll_to(&mut self, year: i32) {
+        while self.year < year {
+            let unused = self.remaining();
+            self.years.push(RoomYear {
+                account: self.account.clone(),
+                year: self.year,
+                available: self.available.clone(),
+                contributed: self.contributed.clone(),
+                unused: unused.clone(),
+            });
+
+            let carried = if self.limit.carry_forward {
+                unused.max(Money::zero())
+            } else {
+                Money::zero()
+            };
+            self.year += 1;
+            self.available = carried + self.limit.limit(self.year);
+            self.contributed = Money::zero();
+        }
+    }
+}
+
+// keeps track of the contribution room of every registered account as the history runs
+#[derive(Clone, Debug)]
+pub struct ContributionRoom {
+    rooms: Vec<Room>,
+}
+
+impl ContributionRoom {
+    pub fn new(accounts: &Accounts, year: i32) -> ContributionRoom {
+        let rooms = accounts.clone().fold_with_path(Vec::new(), |mut rooms, path, account| {
+            if let Account::Simple(SimpleAccount { contribution_limit: Some(limit), .. }) =
+                account {
+                rooms.push(Room::new(String::from(path), limit, year));
+            }
+            rooms
+        });
+        ContributionRoom { rooms: rooms }
+    }
+
+    // money coming in from anywhere other than equity (like interest) is a contribution
+    pub fn limit(&mut self,
+                 transaction: Transaction,
+                 accounts: &Accounts,
+                 warnings: &mut Vec<Warning>)
+                 -> Result<Vec<Transaction>> {
+        if transaction.from.starts_with("equity:") || transaction.from == transaction.to {
+            return Ok(vec![transaction]);
+        }
+        let index = self.rooms.iter().position(|room| room.account == transaction.to);
+        let room = match index {
+            Some(index) => &mut self.rooms[index],
+            None => return Ok(vec![transaction]),
+        };
+
+        room.roll_to(transaction.date.year());
+
+        let amount = transaction.eval_from_amount(accounts)?;
+        let remaining = room.remaining().max(Money::zero());
+        if amount <= remaining {
+            room.contributed += amount;
+            return Ok(vec![transaction]);
+        }
+
+        // the parts keep everything else about the transaction, like its tags
+        let excess = amount.clone() - remaining.clone();
+        let mut allowed = transaction.clone();
+        allowed.amount = Amount::Money(remaining.clone());
+        match room.limit.excess.clone().unwrap_or(Excess::Clip) {
+            Excess::Clip => {
+                let message = format!("clipped {} over the contribution limit", excess);
+                warnings.push(Warning::new(transaction.date, transaction.to.clone(), message));
+                room.contributed += remaining;
+                Ok(vec![allowed])
+            }
+            Excess::Redirect(overflow) => {
+                let message = format!("redirected {} over the contribution limit to {}",
+                                      excess,
+                                      overflow);
+                warnings.push(Warning::new(transaction.date, transaction.to.clone(), message));
+                room.contributed += remaining;
+                let mut redirected = transaction;
+                redirected.amount = Amount::Money(excess);
+                redirected.to = overflow;
+                Ok(vec![allowed, redirected])
+            }
+            Excess::Flag => {
+                let message = format!("contributed {} over the contribution limit", excess);
+                warnings.push(Warning::new(transaction.date, transaction.to.clone(), message));
+                room.contributed += amount;
+                Ok(vec![transaction])
+            }
+        }
+    }
+
+    pub fn report(&self, until: i32) -> Vec<RoomYear> {
+        let mut result = Vec::new();
+        for room in &self.rooms {
+            let mut room = room.clone();
+            room.roll_to(until + 1);
+            result.extend(room.years);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accounts(carry_forward: bool, excess: Excess) -> Accounts {
+        let mut tfsa = SimpleAccount::new(Money::zero());
+        tfsa.contribution_limit = Some(ContributionLimit {
+            limits: vec![AnnualLimit {
+                             year: 2017,
+                             amount: Money::from(5000),
+                         }],
+            carry_forward: carry_forward,
+            room: None,
+            excess: Some(excess),
+        });
+        let mut accounts = Accounts::root();
+        accounts.create_account(String::from("assets:tfsa"), Account::Simple(tfsa)).unwrap();
+        accounts
+    }
+
+    fn contribution(amount: i32, year: i32) -> Transaction {
+        Transaction::new(Amount::Money(Money::from(amount)),
+                         String::from("assets:chequing"),
+                         String::from("assets:tfsa"),
+                         NaiveDate::from_ymd(year, 6, 1))
+            .with_tags(vec![String::from("deductible")])
+    }
+
+    fn amounts(transactions: &[Transaction]) -> Vec<(Money, String)> {
+        transactions.iter()
+            .map(|t| match t.amount {
+                Amount::Money(ref money) => (money.clone(), t.to.clone()),
+                Amount::Percent(_) => panic!("expected money"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_yearly_limit_clips() {
+        let accounts = accounts(false, Excess::Clip);
+        let mut room = ContributionRoom::new(&accounts, 2017);
+        let mut warnings = Vec::new();
+
+        let allowed = room.limit(contribution(3000, 2017), &accounts, &mut warnings).unwrap();
+        assert_eq!(amounts(&allowed), vec![(Money::from(3000), String::from("assets:tfsa"))]);
+        let clipped = room.limit(contribution(3000, 2017), &accounts, &mut warnings).unwrap();
+        assert_eq!(amounts(&clipped), vec![(Money::from(2000), String::from("assets:tfsa"))]);
+        assert_eq!(clipped[0].tags, vec![String::from("deductible")]);
+        assert_eq!(warnings.len(), 1);
+
+        // the limit comes back the next year, without the unused room
+        let next = room.limit(contribution(6000, 2018), &accounts, &mut warnings).unwrap();
+        assert_eq!(amounts(&next), vec![(Money::from(5000), String::from("assets:tfsa"))]);
+    }
+
+    #[test]
+    fn test_unused_room_carries_forward() {
+        let accounts = accounts(true, Excess::Clip);
+        let mut room = ContributionRoom::new(&accounts, 2017);
+        let mut warnings = Vec::new();
+
+        room.limit(contribution(1000, 2017), &accounts, &mut warnings).unwrap();
+        let next = room.limit(contribution(10000, 2018), &accounts, &mut warnings).unwrap();
+        assert_eq!(amounts(&next), vec![(Money::from(9000), String::from("assets:tfsa"))]);
+
+        let years = room.report(2018);
+        assert_eq!(years[0].unused, Money::from(4000));
+        assert_eq!(years[1].available, Money::from(9000));
+    }
+
+    #[test]
+    fn test_over_contribution_is_redirected() {
+        let accounts = accounts(false, Excess::Redirect(String::from("assets:savings")));
+        let mut room = ContributionRoom::new(&accounts, 2017);
+        let mut warnings = Vec::new();
+
+        let split = room.limit(contribution(7000, 2017), &accounts, &mut warnings).unwrap();
+        assert_eq!(amounts(&split),
+                   vec![(Money::from(5000), String::from("assets:tfsa")),
+                        (Money::from(2000), String::from("assets:savings"))]);
+        assert!(split.iter().all(|t| t.tags == vec![String::from("deductible")]));
+        assert_eq!(warnings.len(), 1);
+    }
+}
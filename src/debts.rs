@@ -0,0 +1,222 @@
+use std::cmp::Ordering;
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+use plan::Frequency;
+use strategy::*;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DebtPayoff {
+    pub budget: Money,
+    pub from: String,
+    pub order: PayoffOrder,
+    pub debts: Vec<Debt>,
+    pub frequency: Frequency,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Debt {
+    pub account: String,
+    pub minimum: Money,
+    pub interest_rate: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PayoffOrder {
+    // highest interest rate first
+    Avalanche,
+    // smallest balance first
+    Snowball,
+    // the order the debts are listed in
+    Custom,
+}
+
+// liabilities are stored as positive balances, so paying one down takes money out of both
+// the account paying and the debt. both sides are balanced against a clearing account.
+pub fn payment(amount: Money, from: &str, debt: &str, date: NaiveDate) -> Vec<Transaction> {
+    let clearing = format!("equity:payments:{}", debt);
+    vec![Transaction::new(Amount::Money(amount.clone()),
+                          String::from(from),
+                          clearing.clone(),
+                          date),
+         Transaction::new(Amount::Money(amount), String::from(debt), clearing, date)]
+}
+
+impl DebtPayoff {
+    fn prioritized(&self, accounts: &Accounts) -> Result<Vec<(&Debt, Money)>> {
+        let mut debts = Vec::new();
+        for debt in &self.debts {
+            let balance = accounts.get(&debt.account)?.sum();
+            if balance > Money::zero() {
+                debts.push((debt, balance));
+            }
+        }
+
+        match self.order {
+            PayoffOrder::Avalanche => {
+                debts.sort_by(|&(left, _), &(right, _)| {
+                    let left = left.interest_rate.unwrap_or(0.0);
+                    let right = right.interest_rate.unwrap_or(0.0);
+                    right.partial_cmp(&left).unwrap_or(Ordering::Equal)
+                })
+            }
+            PayoffOrder::Snowball => {
+                debts.sort_by(|&(_, ref left), &(_, ref right)| {
+                    left.partial_cmp(right).unwrap_or(Ordering::Equal)
+                })
+            }
+            PayoffOrder::Custom => {}
+        }
+
+        Ok(debts)
+    }
+
+    // every debt gets its minimum payment, then whatever is left of the budget goes to the
+    // debts in priority order. once a debt is cleared its minimum is freed up for the rest.
+    pub fn payments(&self, accounts: &Accounts) -> Result<Vec<(String, Money)>> {
+        let debts = self.prioritized(accounts)?;
+        let mut remaining = self.budget.clone();
+
+        let mut payments = Vec::new();
+        for &(debt, ref balance) in &debts {
+            let payment = debt.minimum.clone().min(balance.clone()).min(remaining.clone());
+            remaining -= payment.clone();
+            payments.push((debt.account.clone(), payment));
+        }
+
+        for (&(_, ref balance), &mut (_, ref mut payment)) in debts.iter()
+            .zip(payments.iter_mut()) {
+            if remaining <= Money::zero() {
+                break;
+            }
+            let extra = (balance.clone() - payment.clone()).min(remaining.clone());
+            remaining -= extra.clone();
+            *payment += extra;
+        }
+
+        Ok(payments.into_iter()
+            .filter(|&(_, ref payment)| *payment > Money::zero())
+            .collect())
+    }
+}
+
+pub struct DebtPayoffStrategy {
+    schedule: Schedule,
+    rule: DebtPayoff,
+}
+
+impl From<DebtPayoff> for DebtPayoffStrategy {
+    fn from(rule: DebtPayoff) -> DebtPayoffStrategy {
+        DebtPayoffStrategy {
+            schedule: Schedule::new(rule.frequency.clone(), rule.start_date, rule.end_date),
+            rule: rule,
+        }
+    }
+}
+
+impl Strategy for DebtPayoffStrategy {
+    fn next_date(&self) -> Option<NaiveDate> {
+        self.schedule.peek()
+    }
+
+    fn run(&mut self, accounts: &Accounts) -> Result<Vec<Transaction>> {
+        let date = match self.schedule.advance() {
+            Some(date) => date,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut transactions = Vec::new();
+        for (debt, amount) in self.rule.payments(accounts)? {
+            transactions.extend(payment(amount, &self.rule.from, &debt, date));
+        }
+        Ok(transactions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plan::*;
+
+    fn accounts() -> Accounts {
+        let mut accounts = Accounts::root();
+        accounts.create_account(String::from("liabilities:card"),
+                                Account::Simple(SimpleAccount::new(Money::from(1000))))
+            .unwrap();
+        accounts.create_account(String::from("liabilities:car"),
+                                Account::Simple(SimpleAccount::new(Money::from(300))))
+            .unwrap();
+        accounts
+    }
+
+    fn rule(order: PayoffOrder) -> DebtPayoff {
+        DebtPayoff {
+            budget: Money::from(200),
+            from: String::from("assets:chequing"),
+            order: order,
+            debts: vec![Debt {
+                            account: String::from("liabilities:card"),
+                            minimum: Money::from(25),
+                            interest_rate: Some(0.19),
+                        },
+                        Debt {
+                            account: String::from("liabilities:car"),
+                            minimum: Money::from(50),
+                            interest_rate: Some(0.05),
+                        }],
+            frequency: Frequency::Monthly,
+            start_date: None,
+            end_date: None,
+        }
+    }
+
+    #[test]
+    fn test_avalanche_pays_highest_rate_first() {
+        let payments = rule(PayoffOrder::Avalanche).payments(&accounts()).unwrap();
+        assert_eq!(payments,
+                   vec![(String::from("liabilities:card"), Money::from(150)),
+                        (String::from("liabilities:car"), Money::from(50))]);
+    }
+
+    #[test]
+    fn test_snowball_pays_smallest_balance_first() {
+        let payments = rule(PayoffOrder::Snowball).payments(&accounts()).unwrap();
+        assert_eq!(payments,
+                   vec![(String::from("liabilities:car"), Money::from(175)),
+                        (String::from("liabilities:card"), Money::from(25))]);
+    }
+
+    #[test]
+    fn test_cleared_debt_rolls_forward() {
+        let mut accounts = accounts();
+        accounts.withdraw(String::from("liabilities:car"), Money::from(300)).unwrap();
+        let payments = rule(PayoffOrder::Snowball).payments(&accounts).unwrap();
+        assert_eq!(payments,
+                   vec![(String::from("liabilities:card"), Money::from(200))]);
+    }
+
+    #[test]
+    fn test_missing_debt_fails_the_history() {
+        let plan: Plan = ::serde_yaml::from_str("start_date: 2017-01-01\n\
+                                                 accounts:\n  \
+                                                   assets:\n    \
+                                                     chequing:\n      \
+                                                       amount: 1000\n\
+                                                 rules:\n  \
+                                                   payoff:\n    \
+                                                     budget: 200\n    \
+                                                     from: assets:chequing\n    \
+                                                     order: Avalanche\n    \
+                                                     debts:\n      \
+                                                       - account: liabilities:card\n        \
+                                                         minimum: 25\n    \
+                                                     frequency: Monthly")
+            .unwrap();
+        let dates = DateStream::monthly(Some(NaiveDate::from_ymd(2017, 1, 1))).take(3);
+        assert!(plan.moments(dates).is_err());
+    }
+}
@@ -4,7 +4,10 @@ error_chain! {
         Fmt(::std::fmt::Error);
         Io(::std::io::Error);
         SerdeYaml(::serde_yaml::Error);
+        SerdeJson(::serde_json::Error);
         SetLog(::log::SetLoggerError);
+        Csv(::csv::Error);
+        Regex(::regex::Error);
     }
 
     errors {
@@ -21,5 +24,69 @@ error_chain! {
             display("invalid deposit of {} to {}, {1} is a derived account", amount, path)
         }
         UnwrapNode
+        UnknownRule(name: String) {
+            description("there is no rule with that name")
+            display("there is no rule named '{}'", name)
+        }
+        RuleExists(name: String) {
+            description("there is already a rule with that name")
+            display("there is already a rule named '{}'", name)
+        }
+        NoParameter(name: String) {
+            description("the rule has no parameter to change")
+            display("the rule '{}' has no parameter to change", name)
+        }
+        UnknownScenario(name: String) {
+            description("there is no scenario with that name")
+            display("there is no scenario named '{}'", name)
+        }
+        UnknownIndex(name: String) {
+            description("there is no index with that name")
+            display("there is no index named '{}'", name)
+        }
+        LimitExceeded(account: String, date: ::chrono::NaiveDate) {
+            description("a transfer went past the limit of an account")
+            display("a transfer on {} went past the limit of {}", date, account)
+        }
+        LedgerParse(line: usize, message: String) {
+            description("the ledger journal couldn't be read")
+            display("line {} of the ledger journal has {}", line, message)
+        }
+        MissingColumn(name: String) {
+            description("a column is missing from the import")
+            display("there is no column named '{}' in the import", name)
+        }
+        InvalidRow(line: usize, field: String) {
+            description("a row of the import couldn't be read")
+            display("line {} of the import has an invalid {}", line, field)
+        }
+        UnknownProfile(name: String) {
+            description("there is no import profile with that name")
+            display("there is no import profile named '{}'", name)
+        }
+        NoSolution(low: f64, high: f64) {
+            description("there is no solution in the range")
+            display("there is no solution between {} and {}", low, high)
+        }
+        UnknownFormat(name: String) {
+            description("there is no output format with that name")
+            display("there is no output format named '{}'", name)
+        }
+        UnknownStyle(name: String) {
+            description("there is no chart style with that name")
+            display("there is no chart style named '{}'", name)
+        }
+        SnapshotExists(date: ::chrono::NaiveDate) {
+            description("there is already a snapshot on that day")
+            display("there is already a snapshot on {}", date)
+        }
+        UnknownSnapshot(date: ::chrono::NaiveDate) {
+            description("there is no snapshot on that day")
+            display("there is no snapshot on {}", date)
+        }
+        UnknownAccount(path: String) {
+            description("there is no account with that name")
+            display("there is no account named '{}'", path)
+        }
     }
 }
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use chrono::prelude::*;
+use chrono;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+use plan::*;
+
+pub const OPENING_BALANCES: &'static str = "equity:opening balances";
+
+// a life event (buying a house, having a kid, a new job) changes a lot of things on one day
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LifeEvent {
+    pub date: NaiveDate,
+    #[serde(default)]
+    pub transactions: Vec<OneTimeTransfer>,
+    pub accounts: Option<Accounts>,
+    #[serde(default)]
+    pub start: HashMap<String, Rule>,
+    #[serde(default)]
+    pub end: Vec<String>,
+    #[serde(default)]
+    pub change: HashMap<String, f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OneTimeTransfer {
+    pub amount: Money,
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+// the first date on or after the date that the rule would have happened on anyways,
+// so a changed paycheque still comes on payday
+pub fn next_on_or_after(rule: &Rule, date: NaiveDate) -> Option<NaiveDate> {
+    DateStream::from((rule.frequency(), rule.start_date()))
+        .take_while(|next| rule.end_date().map_or(true, |end_date| *next <= end_date))
+        .find(|next| *next >= date)
+}
+
+fn end_before(rule: &mut Rule, date: NaiveDate) {
+    let day_before = date - chrono::Duration::days(1);
+    let end_date = match rule.end_date() {
+        Some(end_date) if end_date < day_before => end_date,
+        _ => day_before,
+    };
+    rule.set_end_date(Some(end_date));
+}
+
+// turns the events into plain old accounts and rules, the plan has to be a copy without them
+pub fn apply_events(plan: &mut Plan, events: &HashMap<String, LifeEvent>) -> Result<()> {
+    let mut events: Vec<(&String, &LifeEvent)> = events.iter().collect();
+    events.sort_by(|left, right| left.1.date.cmp(&right.1.date).then(left.0.cmp(right.0)));
+
+    // after a change, the name of a rule refers to the newest version of it
+    let mut current: HashMap<String, String> = HashMap::new();
+
+    for (name, event) in events {
+        let date = event.date;
+        let mut index = 0;
+        let mut transfer = |plan: &mut Plan, amount: Money, from: String, to: String, tags| {
+            index += 1;
+            plan.rules.insert(format!("{} ({})", name, index),
+                              Rule::RepeatingMoney(MoneyTransfer {
+                                  amount: amount,
+                                  from: from,
+                                  to: to,
+                                  frequency: Frequency::Once,
+                                  start_date: Some(date),
+                                  end_date: None,
+                                  tags: tags,
+                              }));
+        };
+
+        // new accounts are there from the start with nothing in them, and get their
+        // opening balance on the day of the event. an account that is already there keeps
+        // its settings and balance, and the event adds to it.
+        if let Some(ref accounts) = event.accounts {
+            let opening = accounts.clone()
+                .fold_with_path(Vec::new(), |mut opening, path, account| {
+                    opening.push((String::from(path), account));
+                    opening
+                });
+            for (path, account) in opening {
+                let amount = account.amount();
+                let existing = plan.accounts.get(&path).map(|existing| existing.leaf().is_ok());
+                match (existing, account) {
+                    (Ok(true), Account::Simple(_)) => {}
+                    (Ok(_), _) => return Err(ErrorKind::AlreadyExists(path).into()),
+                    (Err(_), Account::Simple(mut simple)) => {
+                        simple.amount = Money::zero();
+                        plan.accounts.create_account(path.clone(), Account::Simple(simple))?;
+                    }
+                    (Err(_), derived) => plan.accounts.create_account(path.clone(), derived)?,
+                }
+                if amount != Money::zero() {
+                    transfer(plan, amount, String::from(OPENING_BALANCES), path, Vec::new());
+                }
+            }
+        }
+
+        for t in &event.transactions {
+            transfer(plan,
+                     t.amount.clone(),
+                     t.from.clone(),
+                     t.to.clone(),
+                     t.tags.clone());
+        }
+
+        for rule_name in &event.end {
+            let key = current.get(rule_name).cloned().unwrap_or_else(|| rule_name.clone());
+            match plan.rules.get_mut(&key) {
+                Some(rule) => end_before(rule, date),
+                None => return Err(ErrorKind::UnknownRule(rule_name.clone()).into()),
+            }
+        }
+
+        let mut changes: Vec<(&String, &f64)> = event.change.iter().collect();
+        changes.sort_by(|left, right| left.0.cmp(right.0));
+        for (rule_name, value) in changes {
+            let key = current.get(rule_name).cloned().unwrap_or_else(|| rule_name.clone());
+            let mut changed = match plan.rules.get_mut(&key) {
+                Some(rule) => {
+                    if rule.parameter().is_none() {
+                        return Err(ErrorKind::NoParameter(rule_name.clone()).into());
+                    }
+                    let changed = rule.clone();
+                    end_before(rule, date);
+                    changed
+                }
+                None => return Err(ErrorKind::UnknownRule(rule_name.clone()).into()),
+            };
+            let start_date = next_on_or_after(&changed, date).unwrap_or(date);
+            changed.set_start_date(Some(start_date));
+            changed.set_parameter(*value);
+
+            let changed_name = format!("{} ({})", rule_name, name);
+            plan.rules.insert(changed_name.clone(), changed);
+            current.insert(rule_name.clone(), changed_name);
+        }
+
+        for (rule_name, rule) in &event.start {
+            let mut rule = rule.clone();
+            if rule.start_date().is_none() {
+                rule.set_start_date(Some(date));
+            }
+            if plan.rules.contains_key(rule_name) {
+                return Err(ErrorKind::RuleExists(rule_name.clone()).into());
+            }
+            plan.rules.insert(rule_name.clone(), rule);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn salary(amount: i32) -> Rule {
+        Rule::RepeatingMoney(MoneyTransfer {
+            amount: Money::from(amount),
+            from: String::from("income:job"),
+            to: String::from("assets:chequing"),
+            frequency: Frequency::Annually,
+            start_date: Some(NaiveDate::from_ymd(2017, 1, 1)),
+            end_date: None,
+            tags: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_change_continues_schedule() {
+        let mut plan: Plan = ::serde_yaml::from_str("accounts: {}\nrules: {}").unwrap();
+        plan.rules.insert(String::from("salary"), salary(50000));
+
+        let mut change = HashMap::new();
+        change.insert(String::from("salary"), 60000.0);
+        let mut events = HashMap::new();
+        events.insert(String::from("promotion"),
+                      LifeEvent {
+                          date: NaiveDate::from_ymd(2019, 6, 1),
+                          transactions: Vec::new(),
+                          accounts: None,
+                          start: HashMap::new(),
+                          end: Vec::new(),
+                          change: change,
+                      });
+        apply_events(&mut plan, &events).unwrap();
+
+        let original = &plan.rules["salary"];
+        assert_eq!(original.end_date(), Some(NaiveDate::from_ymd(2019, 5, 31)));
+        let changed = &plan.rules["salary (promotion)"];
+        assert_eq!(changed.start_date(), Some(NaiveDate::from_ymd(2020, 1, 1)));
+        assert_eq!(changed.parameter(), Some(60000.0));
+    }
+
+    #[test]
+    fn test_change_follows_the_plan_start_date() {
+        let plan: Plan = ::serde_yaml::from_str("start_date: 2017-01-01\n\
+                                                 accounts: {}\n\
+                                                 rules:\n  \
+                                                   salary:\n    \
+                                                     amount: 50000\n    \
+                                                     from: income:job\n    \
+                                                     to: assets:chequing\n    \
+                                                     frequency: Annually\n\
+                                                 events:\n  \
+                                                   promotion:\n    \
+                                                     date: 2019-06-01\n    \
+                                                     change:\n      \
+                                                       salary: 60000")
+            .unwrap();
+        let plan = plan.with_events().unwrap();
+
+        assert_eq!(plan.rules["salary"].start_date(), Some(NaiveDate::from_ymd(2017, 1, 1)));
+        assert_eq!(plan.rules["salary"].end_date(), Some(NaiveDate::from_ymd(2019, 5, 31)));
+        let changed = &plan.rules["salary (promotion)"];
+        assert_eq!(changed.start_date(), Some(NaiveDate::from_ymd(2020, 1, 1)));
+
+        let dates = DateStream::yearly(Some(NaiveDate::from_ymd(2017, 1, 1))).take(4);
+        let moments = plan.moments(dates).unwrap();
+        assert_eq!(moments[3].1.balance("assets:chequing").unwrap(), Money::from(210000));
+    }
+
+    #[test]
+    fn test_existing_account_is_added_to() {
+        let mut plan: Plan = ::serde_yaml::from_str("start_date: 2017-01-01\n\
+                                                     accounts:\n  \
+                                                       assets:\n    \
+                                                         savings:\n      \
+                                                           amount: 1000\n\
+                                                     rules: {}")
+            .unwrap();
+        let event: LifeEvent = ::serde_yaml::from_str("date: 2018-01-01\n\
+                                                       accounts:\n  \
+                                                         assets:\n    \
+                                                           savings:\n      \
+                                                             amount: 500")
+            .unwrap();
+        let mut events = HashMap::new();
+        events.insert(String::from("inheritance"), event);
+        apply_events(&mut plan, &events).unwrap();
+
+        assert_eq!(plan.accounts.balance("assets:savings").unwrap(), Money::from(1000));
+        let moments = plan.moments(DateStream::once(Some(NaiveDate::from_ymd(2018, 6, 1))))
+            .unwrap();
+        assert_eq!(moments[0].1.balance("assets:savings").unwrap(), Money::from(1500));
+    }
+
+    #[test]
+    fn test_starting_a_rule_that_exists_fails() {
+        let mut plan: Plan = ::serde_yaml::from_str("accounts: {}\nrules: {}").unwrap();
+        plan.rules.insert(String::from("salary"), salary(50000));
+
+        let mut start = HashMap::new();
+        start.insert(String::from("salary"), salary(60000));
+        let mut events = HashMap::new();
+        events.insert(String::from("new job"),
+                      LifeEvent {
+                          date: NaiveDate::from_ymd(2019, 6, 1),
+                          transactions: Vec::new(),
+                          accounts: None,
+                          start: start,
+                          end: Vec::new(),
+                          change: HashMap::new(),
+                      });
+        assert!(apply_events(&mut plan, &events).is_err());
+        assert_eq!(plan.rules["salary"].parameter(), Some(50000.0));
+    }
+}
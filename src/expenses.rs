@@ -0,0 +1,157 @@
+use chrono::prelude::*;
+
+use money::Money;
+use plan::*;
+
+// every expense category is an account under here
+pub const EXPENSES: &'static str = "expenses";
+pub const FEES: &'static str = "expenses:fees";
+
+// a budget for a category of spending, which can go up with inflation every year
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Expense {
+    pub category: String,
+    pub amount: Money,
+    pub from: String,
+    pub frequency: Frequency,
+    pub inflation: Option<f64>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+// a yearly percentage of the balance of an account, charged every period (like the mer of a
+// mutual fund)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Fee {
+    pub account: String,
+    pub fee: f64,
+    pub period: Frequency,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+impl Expense {
+    pub fn account(&self) -> String {
+        format!("{}:{}", EXPENSES, self.category)
+    }
+}
+
+impl Fee {
+    pub fn account(&self) -> String {
+        format!("{}:{}", FEES, self.account)
+    }
+}
+
+// fees work just like interest, except that the money goes out of the account
+impl From<Fee> for CompoundingInterest {
+    fn from(fee: Fee) -> CompoundingInterest {
+        CompoundingInterest {
+            account: fee.account,
+            interest_rate: fee.fee,
+            period: fee.period,
+            start_date: fee.start_date,
+            end_date: fee.end_date,
+            schedule: Vec::new(),
+            accrual: None,
+        }
+    }
+}
+
+// the number of whole years between two dates
+pub fn years_between(from: NaiveDate, to: NaiveDate) -> i32 {
+    let years = to.year() - from.year();
+    if (to.month(), to.day()) < (from.month(), from.day()) {
+        years - 1
+    } else {
+        years
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_years_between() {
+        let from = NaiveDate::from_ymd(2017, 6, 15);
+        assert_eq!(years_between(from, NaiveDate::from_ymd(2018, 6, 14)), 0);
+        assert_eq!(years_between(from, NaiveDate::from_ymd(2018, 6, 15)), 1);
+    }
+
+    fn plan(rules: &str) -> Plan {
+        ::serde_yaml::from_str(&format!("start_date: 2017-01-01\n\
+                                         accounts:\n  \
+                                           assets:\n    \
+                                             chequing:\n      \
+                                               amount: 10000\n    \
+                                             rrsp:\n      \
+                                               amount: 12000\n\
+                                         rules:\n{}",
+                                        rules))
+            .unwrap()
+    }
+
+    fn cents(money: Money) -> f64 {
+        (money.to_f64() * 100.0).round()
+    }
+
+    #[test]
+    fn test_expense_goes_up_with_inflation() {
+        let plan = plan("  food:\n    \
+                           category: food\n    \
+                           amount: 100\n    \
+                           from: assets:chequing\n    \
+                           frequency: Annually\n    \
+                           inflation: 0.1\n    \
+                           start_date: 2017-01-01");
+        let moments = plan.moments(DateStream::yearly(Some(plan.start_date())).take(3)).unwrap();
+        let food: Vec<f64> = moments.iter()
+            .map(|&(_, ref accounts)| cents(accounts.balance("expenses:food").unwrap()))
+            .collect();
+        assert_eq!(food, vec![10000.0, 21000.0, 33100.0]);
+        assert_eq!(cents(moments[2].1.balance("assets:chequing").unwrap()), 966900.0);
+    }
+
+    #[test]
+    fn test_fee_charges_a_share_of_the_balance_every_period() {
+        let plan = plan("  mer:\n    \
+                           account: assets:rrsp\n    \
+                           fee: 0.12\n    \
+                           period: Monthly\n    \
+                           start_date: 2017-02-01");
+        let moments = plan.moments(DateStream::monthly(Some(plan.start_date())).take(3)).unwrap();
+        let fees: Vec<f64> = moments.iter()
+            .map(|&(_, ref accounts)| {
+                let fees = accounts.balance("expenses:fees:assets:rrsp");
+                cents(fees.unwrap_or_else(|_| Money::zero()))
+            })
+            .collect();
+        // 1% a month, of what is left after the month before
+        assert_eq!(fees, vec![0.0, 12000.0, 23880.0]);
+        assert_eq!(cents(moments[2].1.balance("assets:rrsp").unwrap()), 1176120.0);
+    }
+
+    #[test]
+    fn test_start_and_end_dates() {
+        let plan = plan("  food:\n    \
+                           category: food\n    \
+                           amount: 50\n    \
+                           from: assets:chequing\n    \
+                           frequency: Monthly\n    \
+                           start_date: 2017-03-01\n    \
+                           end_date: 2017-05-31\n  \
+                         mer:\n    \
+                           account: assets:rrsp\n    \
+                           fee: 0.12\n    \
+                           period: Monthly\n    \
+                           start_date: 2017-02-01\n    \
+                           end_date: 2017-02-28");
+        let moments = plan.moments(DateStream::once(Some(NaiveDate::from_ymd(2017, 12, 31))))
+            .unwrap();
+        let accounts = &moments[0].1;
+        assert_eq!(accounts.balance("expenses:food").unwrap(), Money::from(150));
+        assert_eq!(cents(accounts.balance("expenses:fees:assets:rrsp").unwrap()), 12000.0);
+    }
+}
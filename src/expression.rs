@@ -1,11 +1,12 @@
 use std::fmt;
 use std::marker::PhantomData;
+use std::str::FromStr;
 
 use serde::ser::{Serialize, Serializer};
 use serde::de::{Deserialize, Deserializer, Visitor, Error};
 
-use combine::char::{char, letter, spaces};
-use combine::{many1, parser, try, Parser};
+use combine::char::{char, digit, letter, spaces, string};
+use combine::{eof, many1, optional, parser, try, Parser};
 use combine::combinator::FnParser;
 use combine::primitives::{State, Stream, ParseResult};
 
@@ -28,6 +29,116 @@ impl fmt::Display for Expr {
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum Comparison {
+    GreaterOrEqual,
+    LessOrEqual,
+    Greater,
+    Less,
+}
+
+// a condition compares an expression to a number, like 'assets:savings >= 60000'
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    pub expr: Expr,
+    pub comparison: Comparison,
+    pub value: f64,
+}
+
+impl Condition {
+    pub fn holds(&self, value: f64) -> bool {
+        match self.comparison {
+            Comparison::GreaterOrEqual => value >= self.value,
+            Comparison::LessOrEqual => value <= self.value,
+            Comparison::Greater => value > self.value,
+            Comparison::Less => value < self.value,
+        }
+    }
+
+    // how far the value is from making the condition true
+    pub fn shortfall(&self, value: f64) -> f64 {
+        match self.comparison {
+            Comparison::GreaterOrEqual |
+            Comparison::Greater => (self.value - value).max(0.0),
+            Comparison::LessOrEqual |
+            Comparison::Less => (value - self.value).max(0.0),
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Comparison::*;
+
+        match *self {
+            GreaterOrEqual => write!(f, ">="),
+            LessOrEqual => write!(f, "<="),
+            Greater => write!(f, ">"),
+            Less => write!(f, "<"),
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {}", self.expr, self.comparison, self.value)
+    }
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Expr, String> {
+        match Expression::expr().skip(eof()).parse(State::new(s)) {
+            Ok((expr, _)) => Ok(expr),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Condition, String> {
+        match Expression::condition().skip(eof()).parse(State::new(s)) {
+            Ok((condition, _)) => Ok(condition),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+impl Serialize for Condition {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl Deserialize for Condition {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        deserializer.deserialize(ConditionVisitor)
+    }
+}
+
+struct ConditionVisitor;
+
+impl Visitor for ConditionVisitor {
+    type Value = Condition;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a math expression referencing accounts compared to a number")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where E: Error
+    {
+        v.parse().map_err(E::custom)
+    }
+}
+
 impl Serialize for Expr {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         where S: Serializer
@@ -56,10 +167,7 @@ impl Visitor for ExprVisitor {
     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
         where E: Error
     {
-        match Expression::expr().parse(State::new(v)) {
-            Ok((expr, _)) => Ok(expr),
-            Err(e) => Err(Error::custom(e)),
-        }
+        v.parse().map_err(E::custom)
     }
 }
 
@@ -122,6 +230,63 @@ impl<I> Expression<I>
         sub.parse_stream(input)
     }
 
+    fn number() -> ExprParser<f64, I> {
+        fn_parser(Expression::<I>::number_)
+    }
+
+    fn number_(input: I) -> ParseResult<f64, I>
+        where I: Stream<Item = char>
+    {
+        optional(char('-'))
+            .and(many1(digit()))
+            .and(optional(char('.').with(many1(digit()))))
+            .skip(spaces())
+            .map(|((sign, whole), fraction): ((Option<char>, String), Option<String>)| {
+                format!("{}{}.{}",
+                        sign.map_or(String::new(), |sign| sign.to_string()),
+                        whole,
+                        fraction.unwrap_or_else(|| String::from("0")))
+                    .parse()
+                    .unwrap()
+            })
+            .parse_stream(input)
+    }
+
+    fn comparison() -> ExprParser<Comparison, I> {
+        fn_parser(Expression::<I>::comparison_)
+    }
+
+    fn comparison_(input: I) -> ParseResult<Comparison, I>
+        where I: Stream<Item = char>
+    {
+        try(string(">=")).map(|_| Comparison::GreaterOrEqual)
+            .or(try(string("<=")).map(|_| Comparison::LessOrEqual))
+            .or(char('>').map(|_| Comparison::Greater))
+            .or(char('<').map(|_| Comparison::Less))
+            .skip(spaces())
+            .parse_stream(input)
+    }
+
+    fn condition() -> ExprParser<Condition, I> {
+        fn_parser(Expression::<I>::condition_)
+    }
+
+    fn condition_(input: I) -> ParseResult<Condition, I>
+        where I: Stream<Item = char>
+    {
+        Expression::expr()
+            .and(Expression::comparison())
+            .and(Expression::number())
+            .map(|((expr, comparison), value)| {
+                Condition {
+                    expr: expr,
+                    comparison: comparison,
+                    value: value,
+                }
+            })
+            .parse_stream(input)
+    }
+
     fn expr() -> ExprParser<Expr, I> {
         fn_parser(Expression::<I>::expr_)
     }
@@ -177,6 +342,17 @@ mod tests {
                                            Box::new(Expr::Id(String::from("liabilities")))));
     }
 
+    #[test]
+    fn test_parse_condition() {
+        assert_successful_parse!(condition,
+                                 "assets:savings >= 60000.50",
+                                 Condition {
+                                     expr: Expr::Id(String::from("assets:savings")),
+                                     comparison: Comparison::GreaterOrEqual,
+                                     value: 60000.5,
+                                 });
+    }
+
     #[test]
     fn test_parse_complicated_expression() {
         assert_successful_parse!(add,
@@ -187,4 +363,22 @@ mod tests {
                                         Box::new(Expr::Id(String::from("assets:bank"))),
                                         Box::new(Expr::Id(String::from("liabilities:debt")))))));
     }
+
+    #[test]
+    fn test_parse_negative_condition() {
+        assert_successful_parse!(condition,
+                                 "liabilities < -0.5",
+                                 Condition {
+                                     expr: Expr::Id(String::from("liabilities")),
+                                     comparison: Comparison::Less,
+                                     value: -0.5,
+                                 });
+    }
+
+    #[test]
+    fn test_condition_must_use_all_of_the_input() {
+        assert!("assets > 5".parse::<Condition>().is_ok());
+        assert!("assets > 5 garbage".parse::<Condition>().is_err());
+        assert!("assets garbage".parse::<Expr>().is_err());
+    }
 }
@@ -0,0 +1,122 @@
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+use expenses::FEES;
+
+// investment accounts have a book value, which is how much money went into them. the rest of
+// the balance is growth.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RealizedGain {
+    pub date: NaiveDate,
+    pub account: String,
+    pub amount: Money,
+}
+
+fn book_value(accounts: &Accounts, path: &str) -> Option<Money> {
+    match accounts.get(path).and_then(Accounts::leaf) {
+        Ok(&Account::Simple(ref s)) => s.book_value.clone(),
+        _ => None,
+    }
+}
+
+fn set_book_value(accounts: &mut Accounts, path: &str, value: Money) -> Result<()> {
+    if let Accounts::Leaf(Account::Simple(ref mut s)) = *accounts.get_mut(path)? {
+        s.book_value = Some(value);
+    }
+    Ok(())
+}
+
+// updates the book values of the investment accounts a transaction touches, this has to run
+// before the transaction is applied. withdrawals realize gains using the average cost.
+pub fn track_cost_basis(transaction: &Transaction,
+                        accounts: &mut Accounts)
+                        -> Result<Option<RealizedGain>> {
+    // fees come out of the growth, they don't sell anything
+    if transaction.from == transaction.to || transaction.to.starts_with(FEES) {
+        return Ok(None);
+    }
+    let amount = transaction.eval_from_amount(accounts)?;
+
+    let mut realized = None;
+    if let Some(book) = book_value(accounts, &transaction.from) {
+        let market = accounts.get(&transaction.from)?.sum();
+        if market > Money::zero() {
+            let fraction = (amount.to_f64() / market.to_f64()).min(1.0);
+            let cost = Money::from(book.to_f64() * fraction);
+            set_book_value(accounts, &transaction.from, book - cost.clone())?;
+            realized = Some(RealizedGain {
+                date: transaction.date,
+                account: transaction.from.clone(),
+                amount: amount.clone() - cost,
+            });
+        }
+    }
+
+    // growth doesn't add to the book value, only money coming in from outside does
+    if !transaction.from.starts_with("equity:") {
+        if let Some(book) = book_value(accounts, &transaction.to) {
+            set_book_value(accounts, &transaction.to, book + amount)?;
+        }
+    }
+
+    Ok(realized)
+}
+
+pub fn unrealized_gains(accounts: &Accounts) -> Vec<(String, Money)> {
+    accounts.clone().fold_with_path(Vec::new(), |mut gains, path, account| {
+        if let Account::Simple(SimpleAccount { amount, book_value: Some(book), .. }) = account {
+            gains.push((String::from(path), amount - book));
+        }
+        gains
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accounts() -> Accounts {
+        let mut stocks = SimpleAccount::new(Money::from(10000));
+        stocks.book_value = Some(Money::from(6000));
+        let mut accounts = Accounts::root();
+        accounts.create_account(String::from("assets:stocks"), Account::Simple(stocks)).unwrap();
+        accounts.create_account(String::from("assets:savings"),
+                                Account::Simple(SimpleAccount::new(Money::from(1000))))
+            .unwrap();
+        accounts
+    }
+
+    fn transfer(amount: i32, from: &str, to: &str) -> Transaction {
+        Transaction::new(Amount::Money(Money::from(amount)),
+                         String::from(from),
+                         String::from(to),
+                         NaiveDate::from_ymd(2017, 6, 1))
+    }
+
+    #[test]
+    fn test_partial_sale_realizes_average_cost() {
+        let mut accounts = accounts();
+        let sale = transfer(2500, "assets:stocks", "assets:savings");
+        let gain = track_cost_basis(&sale, &mut accounts).unwrap().unwrap();
+        // a quarter of the account was sold, so a quarter of the book value goes with it
+        assert_eq!(gain.amount, Money::from(1000));
+        assert_eq!(gain.account, "assets:stocks");
+        assert_eq!(book_value(&accounts, "assets:stocks"), Some(Money::from(4500)));
+
+        accounts.apply(sale).unwrap();
+        assert_eq!(unrealized_gains(&accounts),
+                   vec![(String::from("assets:stocks"), Money::from(3000))]);
+    }
+
+    #[test]
+    fn test_sale_without_book_value_realizes_nothing() {
+        let mut accounts = accounts();
+        let sale = transfer(500, "assets:savings", "assets:stocks");
+        assert_eq!(track_cost_basis(&sale, &mut accounts).unwrap(), None);
+        // the money going into the stocks is new money, so it adds to their book value
+        assert_eq!(book_value(&accounts, "assets:stocks"), Some(Money::from(6500)));
+        assert_eq!(book_value(&accounts, "assets:savings"), None);
+    }
+}
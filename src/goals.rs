@@ -0,0 +1,153 @@
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+use expression::Condition;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Goal {
+    pub condition: Condition,
+    pub by: NaiveDate,
+}
+
+#[derive(Clone, Debug)]
+pub struct GoalStatus {
+    pub name: String,
+    pub goal: Goal,
+    pub achieved: Option<NaiveDate>,
+    pub shortfall: Money,
+    pub extra_monthly: Option<Money>,
+}
+
+impl GoalStatus {
+    pub fn on_track(&self) -> bool {
+        match self.achieved {
+            Some(date) => date <= self.goal.by,
+            None => false,
+        }
+    }
+}
+
+// the calendar months from one day to another, not counting the last one if its day of the month
+// hasn't come yet
+fn whole_months(from: NaiveDate, to: NaiveDate) -> i32 {
+    let months = (to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32;
+    if to.day() < from.day() { months - 1 } else { months }
+}
+
+// checks a goal against every moment of a history. the extra monthly contribution is how much
+// more would have to go in every month from the start until the target date to close the gap,
+// ignoring growth.
+pub fn evaluate(name: &str,
+                goal: &Goal,
+                start: NaiveDate,
+                moments: &[(NaiveDate, Accounts)])
+                -> Result<GoalStatus> {
+    let mut achieved = None;
+    // a goal that was due before the history starts is as far off as the history starts
+    let mut at_target = None;
+    let mut first = None;
+
+    for &(date, ref accounts) in moments {
+        let value = accounts.evaluate(&goal.condition.expr)?.to_f64();
+        if first.is_none() {
+            first = Some(value);
+        }
+        if achieved.is_none() && goal.condition.holds(value) {
+            achieved = Some(date);
+        }
+        if date <= goal.by {
+            at_target = Some(value);
+        }
+    }
+
+    let shortfall = match at_target.or(first) {
+        Some(value) => goal.condition.shortfall(value),
+        None => 0.0,
+    };
+
+    let months = whole_months(start, goal.by);
+    // there's no monthly amount that makes up for a goal that's already due
+    let extra_monthly = if shortfall <= 0.0 {
+        Some(Money::zero())
+    } else if months >= 1 {
+        Some(Money::from(shortfall / months as f64))
+    } else {
+        None
+    };
+
+    Ok(GoalStatus {
+        name: String::from(name),
+        goal: goal.clone(),
+        achieved: achieved,
+        shortfall: Money::from(shortfall),
+        extra_monthly: extra_monthly,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moments(balances: &[(i32, i32)]) -> Vec<(NaiveDate, Accounts)> {
+        balances.iter()
+            .map(|&(year, balance)| {
+                let mut accounts = Accounts::root();
+                accounts.create_account(String::from("assets:savings"),
+                                        Account::Simple(SimpleAccount::new(Money::from(balance))))
+                    .unwrap();
+                (NaiveDate::from_ymd(year, 1, 1), accounts)
+            })
+            .collect()
+    }
+
+    fn goal(by: i32) -> Goal {
+        Goal {
+            condition: "assets:savings >= 10000".parse().unwrap(),
+            by: NaiveDate::from_ymd(by, 1, 1),
+        }
+    }
+
+    #[test]
+    fn test_goal_achieved_in_time() {
+        let moments = moments(&[(2017, 4000), (2018, 8000), (2019, 12000)]);
+        let status = evaluate("savings", &goal(2020), NaiveDate::from_ymd(2017, 1, 1), &moments)
+            .unwrap();
+        assert_eq!(status.achieved, Some(NaiveDate::from_ymd(2019, 1, 1)));
+        assert!(status.on_track());
+        assert_eq!(status.shortfall, Money::zero());
+        assert_eq!(status.extra_monthly, Some(Money::zero()));
+    }
+
+    #[test]
+    fn test_goal_behind_needs_more_every_month() {
+        let moments = moments(&[(2017, 4000), (2018, 7000), (2019, 9000)]);
+        let status = evaluate("savings", &goal(2019), NaiveDate::from_ymd(2017, 1, 1), &moments)
+            .unwrap();
+        assert_eq!(status.achieved, None);
+        assert!(!status.on_track());
+        assert_eq!(status.shortfall, Money::from(1000));
+        // 1000 over the 24 months until the target
+        assert_eq!(status.extra_monthly.map(|m| (m.to_f64() * 100.0).round()),
+                   Some(4167.0));
+    }
+
+    #[test]
+    fn test_whole_months() {
+        let date = |year, month, day| NaiveDate::from_ymd(year, month, day);
+        assert_eq!(whole_months(date(2017, 1, 1), date(2019, 1, 1)), 24);
+        assert_eq!(whole_months(date(2017, 1, 15), date(2019, 1, 1)), 23);
+        assert_eq!(whole_months(date(2017, 1, 31), date(2017, 2, 28)), 0);
+        assert_eq!(whole_months(date(2017, 3, 1), date(2017, 1, 1)), -2);
+    }
+
+    #[test]
+    fn test_goal_already_due() {
+        let moments = moments(&[(2017, 4000), (2018, 7000)]);
+        let status = evaluate("savings", &goal(2016), NaiveDate::from_ymd(2017, 1, 1), &moments)
+            .unwrap();
+        assert_eq!(status.shortfall, Money::from(6000));
+        assert_eq!(status.extra_monthly, None);
+    }
+}
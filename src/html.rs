@@ -0,0 +1,182 @@
+use std::fmt::Write;
+
+use accounts::*;
+use errors::*;
+use plan::*;
+use chart;
+use chart::ChartStyle;
+use output::{escape, Field, Report};
+use progress::net_worth;
+use reports;
+
+const STYLE: &'static str = "body { font-family: sans-serif; margin: 2em auto; max-width: 60em; \
+                             color: #222; }\n\
+                             table { border-collapse: collapse; margin-bottom: 1em; }\n\
+                             th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; \
+                             text-align: right; }\n\
+                             th:first-child, td:first-child { text-align: left; }\n\
+                             th { background: #f4f4f4; }\n\
+                             .warning { color: #a33; }";
+
+fn kind(rule: &Rule) -> &'static str {
+    match *rule {
+        Rule::RepeatingMoney(_) => "Transfer",
+        Rule::CompoundingInterest(_) => "Interest",
+        Rule::DebtPayoff(_) => "Debt Payoff",
+        Rule::Waterfall(_) => "Waterfall",
+        Rule::Withdrawal(_) => "Withdrawal",
+        Rule::Expense(_) => "Expense",
+        Rule::Fee(_) => "Fee",
+    }
+}
+
+fn section(out: &mut String, title: &str, body: &str) -> Result<()> {
+    writeln!(out, "<h2>{}</h2>\n{}", escape(title), body)?;
+    Ok(())
+}
+
+fn accounts_report(accounts: &Accounts) -> Result<Report> {
+    let balances = accounts.eval()?;
+    let mut paths: Vec<&String> = balances.keys().collect();
+    paths.sort();
+
+    let mut report = Report::new(vec!["Account", "Opening Balance"]);
+    for path in paths {
+        report.add_row(vec![Field::from(path.as_str()), Field::from(balances[path].clone())]);
+    }
+    Ok(report)
+}
+
+fn rules_report(plan: &Plan) -> Report {
+    let mut names: Vec<&String> = plan.rules.keys().collect();
+    names.sort();
+
+    let mut report = Report::new(vec!["Rule", "Kind", "Parameter", "Frequency", "Start", "End"]);
+    for name in names {
+        let rule = &plan.rules[name];
+        let parameter = match (rule.parameter_name(), rule.parameter()) {
+            (Some(parameter), Some(value)) => Field::from(format!("{} = {}", parameter, value)),
+            _ => Field::Empty,
+        };
+        report.add_row(vec![Field::from(name.as_str()),
+                            Field::from(kind(rule)),
+                            parameter,
+                            Field::from(format!("{:?}", rule.frequency())),
+                            Field::from(rule.start_date()),
+                            Field::from(rule.end_date())]);
+    }
+    report
+}
+
+// a page with everything about the plan on it, that can be opened anywhere without anything else
+pub fn report(plan: &Plan, years: usize) -> Result<String> {
+    let opening = plan.with_events()?.accounts;
+    let account_names = opening.get_account_names();
+
+    let mut history = plan.history(DateStream::yearly(Some(plan.start_date())).take(years))?;
+    let moments: Vec<_> = history.by_ref().collect();
+    history.take_failure()?;
+
+    let mut out = String::new();
+    writeln!(out,
+             "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+              <title>Financial Plan</title>\n<style>\n{}\n</style>\n</head>\n<body>\n\
+              <h1>Financial Plan</h1>",
+             STYLE)?;
+
+    let mut summary = String::new();
+    writeln!(summary, "<ul>")?;
+    writeln!(summary,
+             "<li>Starts on {} and runs for {} years</li>",
+             plan.start_date(),
+             years)?;
+    writeln!(summary,
+             "<li>{} accounts, {} rules, {} goals and {} scenarios</li>",
+             account_names.len(),
+             plan.rules.len(),
+             plan.goals.len(),
+             plan.scenarios.len())?;
+    writeln!(summary,
+             "<li>Net worth on {}: {}</li>",
+             plan.start_date(),
+             net_worth(&opening.eval()?))?;
+    if let Some(&(date, ref last)) = moments.last() {
+        writeln!(summary, "<li>Net worth on {}: {}</li>", date, net_worth(&last.eval()?))?;
+    }
+    writeln!(summary, "</ul>")?;
+    section(&mut out, "Summary", &summary)?;
+
+    section(&mut out, "Accounts", &accounts_report(&opening)?.html())?;
+    section(&mut out, "Rules", &rules_report(plan).html())?;
+
+    // a plan doesn't have to have liabilities
+    let totals: Vec<String> = ["assets", "liabilities"]
+        .iter()
+        .filter(|name| moments.iter().any(|&(_, ref accounts)| accounts.get(name).is_ok()))
+        .map(|name| String::from(*name))
+        .collect();
+    let totals = chart::series(&moments, &totals)?;
+    let assets: Vec<String> =
+        account_names.iter().filter(|name| name.starts_with("assets")).cloned().collect();
+    let mut charts = chart::svg(&totals, ChartStyle::Line)?;
+    // with no asset accounts the series would be every account
+    if assets.is_empty() {
+        charts.push_str("<p>No asset accounts</p>");
+    } else {
+        charts.push_str(&chart::svg(&chart::series(&moments, &assets)?, ChartStyle::Stacked)?);
+    }
+    section(&mut out, "Charts", &charts)?;
+
+    section(&mut out,
+            "Forecast",
+            &reports::forecast_columns(&account_names, &moments)?.html())?;
+
+    if !plan.goals.is_empty() {
+        section(&mut out, "Goals", &reports::goals(plan, years, true)?.html())?;
+    }
+
+    let mut warnings = String::new();
+    for warning in history.warnings() {
+        writeln!(warnings, "<p class=\"warning\">{}</p>", escape(&warning.to_string()))?;
+    }
+    if warnings.is_empty() {
+        warnings.push_str("<p>None</p>");
+    }
+    section(&mut out, "Warnings", &warnings)?;
+
+    writeln!(out, "</body>\n</html>")?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_is_one_page() {
+        let plan: Plan = ::serde_yaml::from_str("accounts:\n  \
+                                                   assets:\n    \
+                                                     savings:\n      \
+                                                       amount: 1000\n\
+                                                 rules: {}")
+            .unwrap();
+        let page = report(&plan, 2).unwrap();
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<svg"));
+        assert!(page.contains("assets:savings"));
+        assert!(!page.contains("src="));
+        assert!(!page.contains("No asset accounts"));
+    }
+
+    #[test]
+    fn test_no_asset_accounts() {
+        let plan: Plan = ::serde_yaml::from_str("accounts:\n  \
+                                                   liabilities:\n    \
+                                                     visa:\n      \
+                                                       amount: 500\n\
+                                                 rules: {}")
+            .unwrap();
+        let page = report(&plan, 2).unwrap();
+        assert!(page.contains("<p>No asset accounts</p>"));
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use chrono::prelude::*;
+use csv;
+use regex::Regex;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+
+pub const UNCATEGORIZED_EXPENSES: &'static str = "expenses:uncategorized";
+pub const UNCATEGORIZED_INCOME: &'static str = "income:uncategorized";
+
+// how to read the exports of one bank account
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImportProfile {
+    // the account the export is for
+    pub account: String,
+    // the accounts of the other statements in an ofx file, by their account id. statements for
+    // ids that aren't here are for the account above.
+    #[serde(default)]
+    pub accounts: HashMap<String, String>,
+    // the names of the columns in the header row of a csv file
+    pub date: Option<String>,
+    // how dates are written in a csv or qif file
+    pub date_format: Option<String>,
+    pub amount: Option<String>,
+    pub debit: Option<String>,
+    pub credit: Option<String>,
+    pub description: Option<String>,
+    pub delimiter: Option<char>,
+    // credit card exports usually show purchases as positive amounts
+    #[serde(default)]
+    pub negate: bool,
+    // the first mapping whose pattern matches the description is the other side of the
+    // transaction
+    #[serde(default)]
+    pub mappings: Vec<Mapping>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Mapping {
+    pub pattern: String,
+    pub account: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct ImportedRow {
+    pub description: String,
+    pub transaction: Transaction,
+}
+
+// everything read from an export, some formats know what the balances of the accounts were at
+// the end
+#[derive(Clone, Debug)]
+pub struct Statement {
+    pub rows: Vec<ImportedRow>,
+    pub balances: Vec<(String, Money)>,
+}
+
+fn column(headers: &[String], name: &Option<String>) -> Result<Option<usize>> {
+    match *name {
+        Some(ref name) => {
+            match headers.iter().position(|header| header.trim() == name.as_str()) {
+                Some(index) => Ok(Some(index)),
+                None => Err(ErrorKind::MissingColumn(name.clone()).into()),
+            }
+        }
+        None => Ok(None),
+    }
+}
+
+// amounts can have currency signs and thousands separators, and be in brackets when negative
+pub fn parse_amount(s: &str) -> Option<f64> {
+    let s = s.trim();
+    if s.is_empty() {
+        return None;
+    }
+    let negative = s.starts_with('(') && s.ends_with(')');
+    let number: String = s.chars().filter(|c| c.is_digit(10) || *c == '.' || *c == '-').collect();
+    number.parse::<f64>().ok().map(|n| if negative { -n } else { n })
+}
+
+impl ImportProfile {
+    // money coming into the account comes from the mapped account, and money leaving goes to it
+    pub fn to_transaction(&self,
+                          date: NaiveDate,
+                          description: &str,
+                          amount: f64,
+                          mappings: &[(Regex, String)])
+                          -> Transaction {
+        let amount = if self.negate { -amount } else { amount };
+        let other = mappings.iter()
+            .find(|&&(ref pattern, _)| pattern.is_match(description))
+            .map(|&(_, ref account)| account.clone())
+            .unwrap_or_else(|| {
+                String::from(if amount < 0.0 {
+                    UNCATEGORIZED_EXPENSES
+                } else {
+                    UNCATEGORIZED_INCOME
+                })
+            });
+
+        if amount < 0.0 {
+            Transaction::new(Amount::Money(Money::from(-amount)),
+                             self.account.clone(),
+                             other,
+                             date)
+        } else {
+            Transaction::new(Amount::Money(Money::from(amount)),
+                             other,
+                             self.account.clone(),
+                             date)
+        }
+    }
+
+    pub fn compiled_mappings(&self) -> Result<Vec<(Regex, String)>> {
+        let mut mappings = Vec::new();
+        for mapping in &self.mappings {
+            mappings.push((Regex::new(&mapping.pattern)?, mapping.account.clone()));
+        }
+        Ok(mappings)
+    }
+
+    pub fn row(&self,
+               date: NaiveDate,
+               description: String,
+               amount: f64,
+               mappings: &[(Regex, String)])
+               -> ImportedRow {
+        ImportedRow {
+            transaction: self.to_transaction(date, &description, amount, mappings),
+            description: description,
+        }
+    }
+
+    // a balance from a statement, which is the other way around when the amounts are
+    pub fn balance(&self, balance: f64) -> Money {
+        Money::from(if self.negate { -balance } else { balance })
+    }
+
+    pub fn read_csv(&self, path: &str) -> Result<Statement> {
+        let mut reader = csv::Reader::from_file(path)?
+            .has_headers(true)
+            .delimiter(self.delimiter.unwrap_or(',') as u8);
+        let headers = reader.headers()?;
+
+        let date_column = self.date.clone().unwrap_or_else(|| String::from("Date"));
+        let date = column(&headers, &Some(date_column))?.unwrap();
+        let amount = column(&headers, &self.amount)?;
+        let debit = column(&headers, &self.debit)?;
+        let credit = column(&headers, &self.credit)?;
+        let description = column(&headers, &self.description)?;
+        if amount.is_none() && debit.is_none() && credit.is_none() {
+            bail!(ErrorKind::MissingColumn(String::from("amount")));
+        }
+        let date_format = self.date_format.clone().unwrap_or_else(|| String::from("%Y-%m-%d"));
+        let mappings = self.compiled_mappings()?;
+
+        let mut rows = Vec::new();
+        for (index, record) in reader.records().enumerate() {
+            let record = record?;
+            let field = |column: Option<usize>| {
+                column.and_then(|c| record.get(c)).map(|f| f.trim()).unwrap_or("")
+            };
+            // the header is the first line
+            let line = index + 2;
+
+            let date = match NaiveDate::parse_from_str(field(Some(date)), &date_format) {
+                Ok(date) => date,
+                Err(_) => bail!(ErrorKind::InvalidRow(line, String::from("date"))),
+            };
+            let value = match amount {
+                Some(_) => parse_amount(field(amount)),
+                None => {
+                    let credit = parse_amount(field(credit)).unwrap_or(0.0);
+                    let debit = parse_amount(field(debit)).unwrap_or(0.0);
+                    Some(credit - debit.abs())
+                }
+            };
+            let value = match value {
+                Some(value) => value,
+                None => bail!(ErrorKind::InvalidRow(line, String::from("amount"))),
+            };
+
+            rows.push(self.row(date, String::from(field(description)), value, &mappings));
+        }
+        Ok(Statement {
+            rows: rows,
+            balances: Vec::new(),
+        })
+    }
+}
+
+impl Statement {
+    // the accounts with the statement applied. when the statement has balances, those are the
+    // balances of the accounts, otherwise the transactions are applied to them. the accounts are
+    // the balances at the end of the start date, so only the transactions after it are applied.
+    pub fn apply(&self, accounts: &Accounts, start_date: NaiveDate) -> Result<Accounts> {
+        let mut accounts = accounts.clone();
+        if self.balances.is_empty() {
+            for row in self.rows.iter().filter(|row| row.transaction.date > start_date) {
+                accounts.apply(row.transaction.clone())?;
+            }
+        }
+        for &(ref path, ref balance) in &self.balances {
+            let mut account = match accounts.get(path) {
+                Ok(&Accounts::Leaf(Account::Simple(ref s))) => s.clone(),
+                _ => SimpleAccount::new(Money::zero()),
+            };
+            account.amount = balance.clone();
+            accounts.set_account(path.clone(), Account::Simple(account))?;
+        }
+        Ok(accounts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("$1,234.50"), Some(1234.5));
+        assert_eq!(parse_amount("(12.00)"), Some(-12.0));
+        assert_eq!(parse_amount("-5"), Some(-5.0));
+        assert_eq!(parse_amount(""), None);
+    }
+
+    fn profile() -> ImportProfile {
+        ::serde_yaml::from_str("account: assets:chequing\n\
+                                mappings:\n  \
+                                  - pattern: (?i)grocer\n    \
+                                    account: expenses:food\n  \
+                                  - pattern: PAYROLL\n    \
+                                    account: income:salary")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_mappings() {
+        let mut profile = profile();
+        let mappings = profile.compiled_mappings().unwrap();
+        let date = NaiveDate::from_ymd(2017, 1, 5);
+
+        let food = profile.to_transaction(date, "Corner Grocery #12", -42.5, &mappings);
+        assert_eq!(food.from, "assets:chequing");
+        assert_eq!(food.to, "expenses:food");
+        assert_eq!(food.amount, Amount::Money(Money::from(42.5)));
+
+        let salary = profile.to_transaction(date, "PAYROLL DEPOSIT", 2000.0, &mappings);
+        assert_eq!(salary.from, "income:salary");
+        assert_eq!(salary.to, "assets:chequing");
+
+        // the first mapping that matches wins, and nothing matching is uncategorized
+        let other = profile.to_transaction(date, "Hardware store", -10.0, &mappings);
+        assert_eq!(other.to, UNCATEGORIZED_EXPENSES);
+
+        profile.negate = true;
+        let refund = profile.to_transaction(date, "Grocer refund", -5.0, &mappings);
+        assert_eq!(refund.from, "expenses:food");
+        assert_eq!(refund.to, "assets:chequing");
+    }
+
+    #[test]
+    fn test_apply_only_after_the_start_date() {
+        let profile = profile();
+        let mappings = profile.compiled_mappings().unwrap();
+        let mut accounts = Accounts::root();
+        accounts.set_account(String::from("assets:chequing"),
+                             Account::Simple(SimpleAccount::new(Money::from(500))))
+            .unwrap();
+        let mut statement = Statement {
+            rows: vec![profile.row(NaiveDate::from_ymd(2017, 1, 5),
+                                   String::from("Grocer"),
+                                   -40.0,
+                                   &mappings),
+                       profile.row(NaiveDate::from_ymd(2017, 2, 5),
+                                   String::from("Grocer"),
+                                   -60.0,
+                                   &mappings)],
+            balances: Vec::new(),
+        };
+
+        // the first purchase is already in the balance on the start date
+        let start_date = NaiveDate::from_ymd(2017, 1, 31);
+        let applied = statement.apply(&accounts, start_date).unwrap();
+        assert_eq!(applied.balance("assets:chequing").unwrap(), Money::from(440));
+        assert_eq!(applied.balance("expenses:food").unwrap(), Money::from(60));
+
+        statement.balances = vec![(profile.account.clone(), profile.balance(750.0))];
+        let applied = statement.apply(&accounts, start_date).unwrap();
+        assert_eq!(applied.balance("assets:chequing").unwrap(), Money::from(750));
+        assert!(applied.balance("expenses:food").is_err());
+    }
+}
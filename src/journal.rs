@@ -0,0 +1,191 @@
+use std::io::Write;
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+use events::OPENING_BALANCES;
+
+// where the difference goes when a transfer between an asset and a liability doesn't balance
+const ADJUSTMENTS: &'static str = "equity:adjustments";
+
+fn is_liability(account: &str) -> bool {
+    account.starts_with("liabilities")
+}
+
+// ledger keeps liabilities as negative balances, they are positive here
+fn signed(account: &str, amount: Money) -> Money {
+    if is_liability(account) { -amount } else { amount }
+}
+
+// amounts are written to the cent, so anything that balances them has to be worked out from
+// what is written or ledger finds them a cent off
+fn cents(amount: Money) -> Money {
+    Money::from((amount.to_f64() * 100.0).round() / 100.0)
+}
+
+fn write_entry<W: Write>(out: &mut W,
+                         date: NaiveDate,
+                         payee: &str,
+                         tags: &[String],
+                         postings: &[(String, Money)])
+                         -> Result<()> {
+    writeln!(out, "{} {}", date.format("%Y/%m/%d"), payee)?;
+    if !tags.is_empty() {
+        writeln!(out, "    ; :{}:", tags.join(":"))?;
+    }
+    for &(ref account, ref amount) in postings {
+        writeln!(out, "    {}  {}", account, amount)?;
+    }
+    writeln!(out, "")?;
+    Ok(())
+}
+
+// the postings of a transaction in ledger. every side is signed on its own, like the opening
+// balances. money going into a debt puts it up, and money coming out of one takes it down. when
+// that doesn't balance, an equity side (like interest, or a payment's clearing account) takes
+// the whole amount, otherwise the difference goes to the adjustments.
+pub fn postings(transaction: &Transaction, amount: &Money) -> Vec<(String, Money)> {
+    let amount = cents(amount.clone());
+    let to = signed(&transaction.to, amount.clone());
+    let from = signed(&transaction.from, -amount);
+    let difference = cents(to.clone() + from.clone());
+    if difference == Money::zero() {
+        vec![(transaction.to.clone(), to), (transaction.from.clone(), from)]
+    } else if transaction.from.starts_with("equity:") {
+        vec![(transaction.to.clone(), to.clone()), (transaction.from.clone(), -to)]
+    } else if transaction.to.starts_with("equity:") {
+        vec![(transaction.to.clone(), -from.clone()), (transaction.from.clone(), from)]
+    } else {
+        vec![(transaction.to.clone(), to),
+             (transaction.from.clone(), from),
+             (String::from(ADJUSTMENTS), -difference)]
+    }
+}
+
+// writes out the opening balances, then every transaction the history applied
+pub fn write_journal<W: Write>(out: &mut W,
+                               date: NaiveDate,
+                               opening: &Accounts,
+                               applied: &[(Transaction, Money)])
+                               -> Result<()> {
+    let mut balances = opening.clone()
+        .fold_with_path(Vec::new(), |mut balances, path, account| {
+            if let Account::Simple(simple) = account {
+                if simple.amount != Money::zero() {
+                    balances.push((String::from(path), signed(path, cents(simple.amount))));
+                }
+            }
+            balances
+        });
+    balances.sort_by(|left, right| left.0.cmp(&right.0));
+    if !balances.is_empty() {
+        let total: Money = balances.iter().map(|&(_, ref amount)| amount.clone()).sum();
+        balances.push((String::from(OPENING_BALANCES), -cents(total)));
+        write_entry(out, date, "Opening Balances", &[], &balances)?;
+    }
+
+    for &(ref transaction, ref amount) in applied {
+        let payee = if transaction.from.starts_with("equity:interest:") {
+            "Interest"
+        } else {
+            "Forecast"
+        };
+        write_entry(out,
+                    transaction.date,
+                    payee,
+                    &transaction.tags,
+                    &postings(transaction, amount))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_liability_postings_balance() {
+        let date = NaiveDate::from_ymd(2017, 1, 1);
+        let interest = Transaction::new(Amount::Money(Money::from(10)),
+                                        String::from("equity:interest:liabilities:visa"),
+                                        String::from("liabilities:visa"),
+                                        date);
+        let postings = postings(&interest, &Money::from(10));
+        assert_eq!(postings[0], (String::from("liabilities:visa"), Money::from(-10)));
+        assert_eq!(postings[1],
+                   (String::from("equity:interest:liabilities:visa"), Money::from(10)));
+    }
+
+    #[test]
+    fn test_asset_and_liability_postings() {
+        let date = NaiveDate::from_ymd(2017, 1, 1);
+        let transfer = |from: &str, to: &str| {
+            let transaction = Transaction::new(Amount::Money(Money::from(100)),
+                                               String::from(from),
+                                               String::from(to),
+                                               date);
+            postings(&transaction, &Money::from(100))
+        };
+
+        // the forecast takes 100 out of chequing and puts the debt up by 100
+        let borrowed = transfer("assets:chequing", "liabilities:visa");
+        assert_eq!(borrowed,
+                   vec![(String::from("liabilities:visa"), Money::from(-100)),
+                        (String::from("assets:chequing"), Money::from(-100)),
+                        (String::from(ADJUSTMENTS), Money::from(200))]);
+
+        // paying a debt down through its clearing account
+        let paid = transfer("liabilities:visa", "equity:payments:liabilities:visa");
+        assert_eq!(paid,
+                   vec![(String::from("equity:payments:liabilities:visa"), Money::from(-100)),
+                        (String::from("liabilities:visa"), Money::from(100))]);
+
+        for postings in vec![borrowed, paid] {
+            let total: Money = postings.into_iter().map(|(_, amount)| amount).sum();
+            assert_eq!(total, Money::zero());
+        }
+    }
+
+    #[test]
+    fn test_amounts_are_balanced_to_the_cent() {
+        let mut opening = Accounts::root();
+        opening.create_account(String::from("assets:savings"),
+                                Account::Simple(SimpleAccount::new(Money::from(1000.004))))
+            .unwrap();
+        opening.create_account(String::from("liabilities:visa"),
+                                Account::Simple(SimpleAccount::new(Money::from(0.333))))
+            .unwrap();
+
+        // a month of interest on 1000.00 at 5%, then the same amount onto the visa
+        let date = NaiveDate::from_ymd(2017, 1, 31);
+        let amount = Money::from(1000.0 * 0.05 / 12.0);
+        let interest = Transaction::new(Amount::Money(amount.clone()),
+                                        String::from("equity:interest:assets:savings"),
+                                        String::from("assets:savings"),
+                                        date);
+        let payment = Transaction::new(Amount::Money(amount.clone()),
+                                       String::from("assets:savings"),
+                                       String::from("liabilities:visa"),
+                                       date);
+
+        let mut out = Vec::new();
+        write_journal(&mut out,
+                      NaiveDate::from_ymd(2017, 1, 1),
+                      &opening,
+                      &[(interest, amount.clone()), (payment, amount)])
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "2017/01/01 Opening Balances\n    \
+                      assets:savings  $1000.00\n    \
+                      liabilities:visa  $-0.33\n    \
+                      equity:opening balances  $-999.67\n\n\
+                    2017/01/31 Interest\n    \
+                      assets:savings  $4.17\n    \
+                      equity:interest:assets:savings  $-4.17\n\n\
+                    2017/01/31 Forecast\n    \
+                      liabilities:visa  $-4.17\n    \
+                      assets:savings  $-4.17\n    \
+                      equity:adjustments  $8.34\n\n");
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use chrono::prelude::*;
+use serde::de::{Deserialize, Deserializer};
+use serde::de::Error as DeError;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+use plan::today;
+
+// only the balance sheet comes over from ledger, the rest is what the plan is for
+const IMPORTED: [&'static str; 2] = ["assets", "liabilities"];
+
+// where the accounts of a plan come from
+#[derive(Clone, Debug, Deserialize)]
+pub struct LedgerSource {
+    pub from_ledger: String,
+    // balances are as of the end of this day, today by default
+    pub as_of: Option<NaiveDate>,
+    // only amounts in this commodity count, all of them do by default
+    pub commodity: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AccountsSource {
+    Ledger(LedgerSource),
+    Accounts(Accounts),
+}
+
+pub fn accounts_or_ledger<D>(deserializer: D) -> ::std::result::Result<Accounts, D::Error>
+    where D: Deserializer
+{
+    match AccountsSource::deserialize(deserializer)? {
+        AccountsSource::Ledger(source) => {
+            import(&source).map_err(|e| D::Error::custom(e.to_string()))
+        }
+        AccountsSource::Accounts(accounts) => Ok(accounts),
+    }
+}
+
+pub fn import(source: &LedgerSource) -> Result<Accounts> {
+    let mut journal = String::new();
+    File::open(&source.from_ledger)?.read_to_string(&mut journal)?;
+    let balances = balances(&journal,
+                            source.as_of.unwrap_or_else(today),
+                            source.commodity.as_ref().map(String::as_str))?;
+    to_accounts(balances)
+}
+
+fn parse_error(line: usize, message: &str) -> Error {
+    ErrorKind::LedgerParse(line, String::from(message)).into()
+}
+
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    // the auxiliary date after the = doesn't matter here
+    let s = s.split('=').next().unwrap_or("");
+    for format in &["%Y/%m/%d", "%Y-%m-%d", "%Y.%m.%d"] {
+        if let Ok(date) = NaiveDate::parse_from_str(s, format) {
+            return Some(date);
+        }
+    }
+    None
+}
+
+// amounts look like '$1,234.56', '-$5', '$-5', '10 CAD' or 'CAD 10'. the cost after an @ and a
+// balance assertion after an = are left off.
+fn parse_amount(s: &str) -> Option<(String, f64)> {
+    let s = s.split(|c| c == '@' || c == '=').next().unwrap_or("").trim();
+    let mut number = String::new();
+    let mut commodity = String::new();
+    let mut negative = false;
+    for c in s.chars() {
+        if c.is_digit(10) || c == '.' {
+            number.push(c);
+        } else if c == '-' {
+            negative = true;
+        } else if c == ',' || c == '"' || c == '+' || c.is_whitespace() {
+            continue;
+        } else {
+            commodity.push(c);
+        }
+    }
+    number.parse::<f64>().ok().map(|n| (commodity, if negative { -n } else { n }))
+}
+
+// accounts are written like 'Assets:Checking', or '(Assets:Checking)' and '[Assets:Checking]'
+// for virtual postings. a posting can start with its status, like '* Assets:Checking'.
+fn account_name(s: &str) -> String {
+    s.trim_start_matches(|c: char| c == '*' || c == '!' || c.is_whitespace())
+        .trim_matches(|c| c == '(' || c == ')' || c == '[' || c == ']')
+        .trim()
+        .to_lowercase()
+}
+
+struct Entry {
+    postings: Vec<(String, Option<(String, f64)>)>,
+    date: NaiveDate,
+    line: usize,
+}
+
+// works out the balance of every account mentioned in the journal at the end of a day
+pub fn balances(journal: &str,
+                as_of: NaiveDate,
+                commodity: Option<&str>)
+                -> Result<HashMap<String, f64>> {
+    let mut balances = HashMap::new();
+    let mut entry: Option<Entry> = None;
+    // periodic and automated transactions aren't real, so their postings are skipped, and so
+    // are the indented sub directives of other directives
+    let mut skipping = false;
+    // without a commodity to pick, there can only be one
+    let mut only: Option<String> = None;
+
+    let mut lines: Vec<&str> = journal.lines().collect();
+    // an empty line at the end closes off the last transaction
+    lines.push("");
+
+    for (index, line) in lines.iter().enumerate() {
+        let number = index + 1;
+        let content = line.split(';').next().unwrap_or("");
+        let indented = line.starts_with(' ') || line.starts_with('\t');
+
+        if line.trim().is_empty() {
+            // the transaction is over, so any posting without an amount balances the rest
+            if let Some(finished) = entry.take() {
+                post(finished, as_of, commodity, &mut balances)?;
+            }
+            skipping = false;
+            continue;
+        }
+
+        if indented {
+            // comments can be indented inside a transaction too
+            if skipping || content.trim().is_empty() {
+                continue;
+            }
+            let entry = match entry {
+                Some(ref mut entry) => entry,
+                None => return Err(parse_error(number, "a posting outside of a transaction")),
+            };
+            // the account and the amount are separated by two spaces or a tab
+            let content = content.trim();
+            let split = content.find("  ").into_iter().chain(content.find('\t')).min();
+            let (account, amount) = match split {
+                Some(i) => (&content[..i], content[i..].trim()),
+                None => (content, ""),
+            };
+            let amount = if amount.is_empty() {
+                None
+            } else {
+                match parse_amount(amount) {
+                    Some(amount) => Some(amount),
+                    None => return Err(parse_error(number, "an amount that isn't a number")),
+                }
+            };
+            if let (None, &Some((ref c, _))) = (commodity, &amount) {
+                match only {
+                    Some(ref only) if only != c => {
+                        return Err(parse_error(number,
+                                               "amounts in more than one commodity, without a \
+                                                commodity to pick"))
+                    }
+                    Some(_) => {}
+                    None => only = Some(c.clone()),
+                }
+            }
+            entry.postings.push((account_name(account), amount));
+            continue;
+        }
+
+        if let Some(finished) = entry.take() {
+            post(finished, as_of, commodity, &mut balances)?;
+        }
+        skipping = true;
+
+        let first = content.split_whitespace().next().unwrap_or("");
+        if first.starts_with(|c: char| c.is_digit(10)) {
+            skipping = false;
+            let date = match parse_date(first) {
+                Some(date) => date,
+                None => return Err(parse_error(number, "a transaction without a date")),
+            };
+            entry = Some(Entry {
+                postings: Vec::new(),
+                date: date,
+                line: number,
+            });
+        } else if first == "account" {
+            let name = content["account".len()..].trim();
+            balances.entry(account_name(name)).or_insert(0.0);
+        }
+        // everything else (comments, prices, commodities, aliases, periodic and automated
+        // transactions) doesn't change balances
+    }
+
+    Ok(balances)
+}
+
+fn post(entry: Entry,
+        as_of: NaiveDate,
+        commodity: Option<&str>,
+        balances: &mut HashMap<String, f64>)
+        -> Result<()> {
+    let elided = entry.postings.iter().filter(|&&(_, ref amount)| amount.is_none()).count();
+    if elided > 1 {
+        return Err(parse_error(entry.line, "more than one posting without an amount"));
+    }
+
+    let counts = |c: &str| commodity.map_or(true, |commodity| commodity == c);
+    let total: f64 = entry.postings
+        .iter()
+        .filter_map(|&(_, ref amount)| amount.as_ref())
+        .filter(|&&(ref c, _)| counts(c))
+        .map(|&(_, value)| value)
+        .sum();
+
+    for (account, amount) in entry.postings {
+        let balance = balances.entry(account).or_insert(0.0);
+        if entry.date > as_of {
+            continue;
+        }
+        match amount {
+            Some((ref c, value)) if counts(c) => *balance += value,
+            Some(_) => {}
+            None => *balance -= total,
+        }
+    }
+    Ok(())
+}
+
+// the balance sheet accounts as a tree. liabilities are credits in ledger, so they are negative
+// there and positive here. money posted to an account that has sub accounts goes into 'other'.
+pub fn to_accounts(balances: HashMap<String, f64>) -> Result<Accounts> {
+    let mut paths: Vec<String> = balances.keys()
+        .filter(|path| IMPORTED.iter().any(|top| path.split(':').next() == Some(top)))
+        .cloned()
+        .collect();
+    paths.sort();
+
+    let mut accounts = Accounts::root();
+    for path in &paths {
+        let mut balance = balances[path];
+        if path.starts_with("liabilities") {
+            balance = -balance;
+        }
+
+        let prefix = format!("{}:", path);
+        let has_children = paths.iter().any(|other| other.starts_with(&prefix));
+        if has_children && balance == 0.0 {
+            continue;
+        }
+        let target = if has_children {
+            format!("{}other", prefix)
+        } else {
+            path.clone()
+        };
+        accounts.create_account(target, Account::Simple(SimpleAccount::new(Money::from(balance))))?;
+    }
+    Ok(accounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_balances() {
+        let journal = "account Assets:Savings\n\
+                       \n\
+                       2017/01/01 * Opening Balances\n    \
+                           Assets:Checking    $1,500.00\n    \
+                           Liabilities:Visa   -$200\n    \
+                           Equity:Opening Balances\n\
+                       \n\
+                       2017-02-01 Groceries ; weekly shop\n    \
+                           Expenses:Food      $50\n    \
+                           Liabilities:Visa\n\
+                       \n\
+                       2018/01/01 Rent\n    \
+                           Expenses:Rent      $1000\n    \
+                           Assets:Checking\n";
+        let balances = balances(journal, NaiveDate::from_ymd(2017, 12, 31), None).unwrap();
+        assert_eq!(balances["assets:checking"], 1500.0);
+        assert_eq!(balances["liabilities:visa"], -250.0);
+        assert_eq!(balances["assets:savings"], 0.0);
+
+        let accounts = to_accounts(balances).unwrap();
+        assert_eq!(accounts.balance("liabilities:visa").unwrap(), Money::from(250));
+        assert!(accounts.get("equity").is_err());
+    }
+
+    #[test]
+    fn test_directives_and_statuses() {
+        let journal = "account Assets:Savings\n    \
+                           note where the emergency fund is\n\
+                       commodity $\n    \
+                           format $1,000.00\n\
+                       \n\
+                       2017/01/01 Opening Balances\n    \
+                           * Assets:Savings    $500\n    \
+                           ! Assets:Checking   $100\n    \
+                           Equity:Opening Balances\n";
+        let balances = balances(journal, NaiveDate::from_ymd(2017, 12, 31), None).unwrap();
+        assert_eq!(balances["assets:savings"], 500.0);
+        assert_eq!(balances["assets:checking"], 100.0);
+        assert_eq!(balances.len(), 3);
+    }
+
+    #[test]
+    fn test_more_than_one_commodity() {
+        let journal = "2017/01/01 Opening Balances\n    \
+                           Assets:Checking     $500\n    \
+                           Assets:Brokerage    10 AAPL\n    \
+                           Equity:Opening Balances\n";
+        let as_of = NaiveDate::from_ymd(2017, 12, 31);
+        assert!(balances(journal, as_of, None).is_err());
+
+        let balances = balances(journal, as_of, Some("$")).unwrap();
+        assert_eq!(balances["assets:checking"], 500.0);
+        assert_eq!(balances["assets:brokerage"], 0.0);
+    }
+}
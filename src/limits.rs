@@ -0,0 +1,223 @@
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+use expenses::FEES;
+
+// the lowest (zero, or an overdraft limit) and highest (a credit limit) an account can go
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BalanceLimit {
+    pub floor: Option<Money>,
+    pub ceiling: Option<Money>,
+    pub policy: Option<LimitPolicy>,
+}
+
+// what happens when a transfer would go past a limit
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum LimitPolicy {
+    // stop the simulation
+    Fail,
+    // only transfer as much as the limit allows
+    Cap,
+    // make up the difference with another account
+    Overdraft(String),
+    // let it through, and charge a fee
+    Fee(Money),
+}
+
+#[derive(Clone, Debug)]
+pub struct LimitHit {
+    pub date: NaiveDate,
+    pub account: String,
+    pub limit: &'static str,
+    pub over: Money,
+    pub action: String,
+}
+
+fn balance_limit(accounts: &Accounts, path: &str) -> Option<BalanceLimit> {
+    match accounts.get(path) {
+        Ok(&Accounts::Leaf(Account::Simple(ref s))) => s.balance_limit.clone(),
+        _ => None,
+    }
+}
+
+// liabilities are positive balances, so borrowing puts money into both the debt and the
+// account, balanced against a clearing account
+fn borrow(amount: Money, from: &str, to: &str, date: NaiveDate) -> Vec<Transaction> {
+    if from.starts_with("liabilities") {
+        let clearing = format!("equity:borrowing:{}", from);
+        vec![Transaction::new(Amount::Money(amount.clone()),
+                              clearing.clone(),
+                              String::from(from),
+                              date),
+             Transaction::new(Amount::Money(amount), clearing, String::from(to), date)]
+    } else {
+        vec![Transaction::new(Amount::Money(amount), String::from(from), String::from(to), date)]
+    }
+}
+
+fn fee(amount: Money, account: &str, ceiling: bool, date: NaiveDate) -> Vec<Transaction> {
+    let expense = format!("{}:{}", FEES, account);
+    if ceiling {
+        borrow(amount, account, &expense, date)
+    } else {
+        vec![Transaction::new(Amount::Money(amount), String::from(account), expense, date)]
+    }
+}
+
+fn with_amount(transaction: &Transaction, amount: Money) -> Transaction {
+    let mut transaction = transaction.clone();
+    transaction.amount = Amount::Money(amount);
+    transaction
+}
+
+// checks a transaction against the floor of the account it comes out of and the ceiling of the
+// account it goes into, and works out what should happen instead
+pub fn enforce(transaction: Transaction,
+               accounts: &Accounts,
+               hits: &mut Vec<LimitHit>)
+               -> Result<Vec<Transaction>> {
+    let from_limit = balance_limit(accounts, &transaction.from);
+    let to_limit = balance_limit(accounts, &transaction.to);
+    if from_limit.is_none() && to_limit.is_none() {
+        return Ok(vec![transaction]);
+    }
+
+    let date = transaction.date;
+    let mut amount = transaction.eval_from_amount(accounts)?;
+    if amount <= Money::zero() || transaction.from == transaction.to {
+        return Ok(vec![transaction]);
+    }
+
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+    let sides = [(from_limit, transaction.from.clone(), false),
+                 (to_limit, transaction.to.clone(), true)];
+    for &(ref limit, ref account, ceiling) in &sides {
+        let limit = match *limit {
+            Some(ref limit) => limit,
+            None => continue,
+        };
+        let balance = accounts.balance(account)?;
+        let over = match (ceiling, &limit.floor, &limit.ceiling) {
+            (false, &Some(ref floor), _) => floor.clone() - (balance - amount.clone()),
+            (true, _, &Some(ref top)) => balance + amount.clone() - top.clone(),
+            _ => continue,
+        };
+        if over <= Money::zero() {
+            continue;
+        }
+
+        let action = match limit.policy.clone().unwrap_or(LimitPolicy::Cap) {
+            LimitPolicy::Fail => {
+                return Err(ErrorKind::LimitExceeded(account.clone(), date).into());
+            }
+            LimitPolicy::Cap => {
+                let over = over.clone().min(amount.clone());
+                amount -= over.clone();
+                format!("capped the transfer by {}", over)
+            }
+            LimitPolicy::Overdraft(ref overdraft) => {
+                if ceiling {
+                    // the part over the limit goes to the overdraft account instead
+                    let over = over.clone().min(amount.clone());
+                    amount -= over.clone();
+                    let mut redirected = with_amount(&transaction, over.clone());
+                    redirected.to = overdraft.clone();
+                    after.push(redirected);
+                    format!("sent {} to {}", over, overdraft)
+                } else {
+                    before.extend(borrow(over.clone(), overdraft, account, date));
+                    format!("covered {} from {}", over, overdraft)
+                }
+            }
+            LimitPolicy::Fee(ref charge) => {
+                after.extend(fee(charge.clone(), account, ceiling, date));
+                format!("charged a fee of {}", charge)
+            }
+        };
+
+        hits.push(LimitHit {
+            date: date,
+            account: account.clone(),
+            limit: if ceiling { "ceiling" } else { "floor" },
+            over: over,
+            action: action,
+        });
+    }
+
+    let mut result = before;
+    if amount > Money::zero() {
+        result.push(with_amount(&transaction, amount));
+    }
+    result.extend(after);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plan::*;
+
+    #[test]
+    fn test_overdraft_covers_shortfall() {
+        let mut chequing = SimpleAccount::new(Money::from(100));
+        chequing.balance_limit = Some(BalanceLimit {
+            floor: Some(Money::zero()),
+            ceiling: None,
+            policy: Some(LimitPolicy::Overdraft(String::from("liabilities:loc"))),
+        });
+        let mut accounts = Accounts::root();
+        accounts.set_account(String::from("assets:chequing"), Account::Simple(chequing)).unwrap();
+
+        let date = NaiveDate::from_ymd(2017, 1, 1);
+        let mut hits = Vec::new();
+        let transactions = enforce(Transaction::new(Amount::Money(Money::from(150)),
+                                                    String::from("assets:chequing"),
+                                                    String::from("expenses:rent"),
+                                                    date),
+                                   &accounts,
+                                   &mut hits)
+            .unwrap();
+
+        for transaction in transactions {
+            accounts.apply(transaction).unwrap();
+        }
+        assert_eq!(hits.len(), 1);
+        assert_eq!(accounts.balance("assets:chequing").unwrap(), Money::zero());
+        assert_eq!(accounts.balance("liabilities:loc").unwrap(), Money::from(50));
+    }
+
+    #[test]
+    fn test_capped_contribution_only_uses_what_got_in() {
+        let plan: Plan = ::serde_yaml::from_str("start_date: 2017-01-01\n\
+                                                 accounts:\n  \
+                                                   assets:\n    \
+                                                     tfsa:\n      \
+                                                       amount: 0\n      \
+                                                       contribution_limit:\n        \
+                                                         limits:\n          \
+                                                           - year: 2017\n            \
+                                                             amount: 5000\n      \
+                                                       balance_limit:\n        \
+                                                         ceiling: 3000\n        \
+                                                         policy: Cap\n\
+                                                 rules:\n  \
+                                                   saving:\n    \
+                                                     amount: 4000\n    \
+                                                     from: income\n    \
+                                                     to: assets:tfsa\n    \
+                                                     frequency: Once\n    \
+                                                     start_date: 2017-02-01")
+            .unwrap();
+        let mut history = plan.history(DateStream::once(Some(NaiveDate::from_ymd(2017, 12, 31))))
+            .unwrap();
+        let moments: Vec<_> = history.by_ref().collect();
+        assert_eq!(moments[0].1.balance("assets:tfsa").unwrap(), Money::from(3000));
+
+        let years = history.contribution_room().report(2017);
+        assert_eq!(years[0].contributed, Money::from(3000));
+        assert_eq!(years[0].unused, Money::from(2000));
+    }
+}
@@ -8,6 +8,7 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_yaml;
+extern crate serde_json;
 extern crate chrono;
 extern crate prettytable;
 extern crate rugflo;
@@ -17,6 +18,8 @@ extern crate combine;
 #[macro_use]
 extern crate log;
 extern crate env_logger;
+extern crate csv;
+extern crate regex;
 
 mod money;
 mod plan;
@@ -24,45 +27,462 @@ mod accounts;
 mod iterators;
 mod errors;
 mod expression;
+mod strategy;
+mod debts;
+mod waterfall;
+mod contributions;
+mod tax;
+mod gains;
+mod withdrawal;
+mod goals;
+mod solve;
+mod sensitivity;
+mod events;
+mod rates;
+mod accrual;
+mod limits;
+mod expenses;
+mod ledger;
+mod journal;
+mod imports;
+mod ofx;
+mod qif;
+mod recurring;
+mod progress;
+mod snapshots;
+mod output;
+mod reports;
+mod chart;
+mod html;
 
 use std::fs::File;
-use clap::{Arg, App, SubCommand};
-use prettytable::Table;
-use prettytable::row::Row;
-use prettytable::cell::Cell;
+use std::io;
+use std::io::{Read, Write};
+use chrono::prelude::*;
+use clap::{Arg, App, ArgMatches, SubCommand};
 
 use plan::*;
 use errors::*;
+use money::Money;
+use gains::unrealized_gains;
+use expression::{Condition, Expr};
+use sensitivity::Variation;
+use imports::{ImportProfile, Statement};
+use progress::Actual;
+use snapshots::SnapshotStore;
+use output::{Field, Format, Output, Report};
+use chart::ChartStyle;
 
-fn print_forecast(plan: &Plan, years: usize) -> Result<()> {
-    let mut table = Table::new();
+fn print_forecast(plan: &Plan, years: usize, flows: bool, output: &mut Output) -> Result<()> {
+    let account_names = plan.with_events()?.accounts.get_account_names();
+    let mut history = plan.history(DateStream::yearly(Some(plan.start_date())).take(years))?;
+    if flows {
+        history = history.with_journal();
+    }
+    let moments: Vec<_> = history.by_ref().collect();
+    history.take_failure()?;
 
-    let mut header = Vec::new();
-    header.push(Cell::new("Date"));
+    // a column for every account is easier to read, other programs get a row for every account
+    // on every date
+    if output.format.is_readable() && !flows {
+        return output.report(&reports::forecast_columns(&account_names, &moments)?);
+    }
+    let journal = if flows { Some(history.journal()) } else { None };
+    output.report(&reports::forecast_rows(&account_names, &moments, journal)?)
+}
+
+fn print_chart(plan: &Plan,
+               years: usize,
+               accounts: &[String],
+               svg: Option<&str>,
+               style: ChartStyle,
+               sparklines: bool)
+               -> Result<()> {
+    let moments = plan.moments(DateStream::yearly(Some(plan.start_date())).take(years))?;
+    let series = chart::series(&moments, accounts)?;
 
-    let account_names = plan.accounts.get_account_names();
-    for name in &account_names {
-        header.push(Cell::new(name));
+    if let Some(path) = svg {
+        File::create(path)?.write_all(chart::svg(&series, style)?.as_bytes())?;
+    }
+    if sparklines {
+        print!("{}", chart::sparklines(&series)?);
     }
-    table.add_row(Row::new(header));
+    Ok(())
+}
 
-    for (date, moment) in plan.history(DateStream::yearly(None).take(years)) {
-        let mut result = Vec::new();
+fn print_room(plan: &Plan, years: usize, output: &mut Output) -> Result<()> {
+    let mut history = plan.history(DateStream::yearly(Some(plan.start_date())).take(years))?;
+    let last = history.by_ref().last();
+    history.take_failure()?;
+    let until = match last {
+        Some((date, _)) => date.year(),
+        None => return Ok(()),
+    };
+
+    let mut report = Report::new(vec!["Account", "Year", "Available", "Contributed", "Unused"]);
+    for year in history.contribution_room().report(until) {
+        report.add_row(vec![Field::from(year.account.as_str()),
+                            Field::from(year.year),
+                            Field::from(year.available.clone()),
+                            Field::from(year.contributed.clone()),
+                            Field::from(year.unused.clone())]);
+    }
+    output.report(&report)?;
+
+    if output.format.is_readable() {
+        for warning in history.warnings() {
+            writeln!(output.writer, "{}", warning)?;
+        }
+    }
+    Ok(())
+}
+
+fn print_gains(plan: &Plan, years: usize, output: &mut Output) -> Result<()> {
+    let mut history = plan.history(DateStream::yearly(Some(plan.start_date())).take(years))?;
+    let moments: Vec<_> = history.by_ref().collect();
+    history.take_failure()?;
+
+    let mut report = Report::new(vec!["Date", "Account", "Realized", "Unrealized"]);
+
+    let mut previous = None;
+    for (date, moment) in moments {
+        let mut unrealized = unrealized_gains(&moment);
+        unrealized.sort_by(|left, right| left.0.cmp(&right.0));
+
+        for (account, gain) in unrealized {
+            let realized: Money = history.realized_gains()
+                .iter()
+                .filter(|g| g.account == account && g.date <= date && Some(g.date) > previous)
+                .map(|g| g.amount.clone())
+                .sum();
+            report.add_row(vec![Field::from(date),
+                                Field::from(account),
+                                Field::from(realized),
+                                Field::from(gain)]);
+        }
+        previous = Some(date);
+    }
+
+    output.report(&report)
+}
 
-        result.push(Cell::new(&format!("{}", date)));
+fn print_warnings(plan: &Plan, years: usize, output: &mut Output) -> Result<()> {
+    let mut history = plan.history(DateStream::yearly(Some(plan.start_date())).take(years))?;
+    for _ in history.by_ref() {}
+    history.take_failure()?;
 
-        let evaluated = moment.eval()?;
-        for name in &account_names {
-            result.push(Cell::new(&format!("{}", evaluated[name])));
+    if output.format == Format::Table {
+        for warning in history.warnings() {
+            writeln!(output.writer, "{}", warning)?;
         }
+        return Ok(());
+    }
 
-        table.add_row(Row::new(result));
+    let mut report = Report::new(vec!["Date", "Account", "Message"]);
+    for warning in history.warnings() {
+        report.add_row(vec![Field::from(warning.date),
+                            Field::from(warning.account.as_str()),
+                            Field::from(warning.message.as_str())]);
     }
+    output.report(&report)
+}
+
+fn print_limits(plan: &Plan, years: usize, output: &mut Output) -> Result<()> {
+    let mut history = plan.history(DateStream::yearly(Some(plan.start_date())).take(years))?;
+    for _ in history.by_ref() {}
+
+    let mut report = Report::new(vec!["Date", "Account", "Limit", "Over By", "Action"]);
+    for hit in history.limit_hits() {
+        report.add_row(vec![Field::from(hit.date),
+                            Field::from(hit.account.as_str()),
+                            Field::from(hit.limit),
+                            Field::from(hit.over.clone()),
+                            Field::from(hit.action.as_str())]);
+    }
+
+    output.report(&report)?;
+    history.take_failure()
+}
+
+fn print_journal(plan: &Plan, years: usize) -> Result<()> {
+    let opening = plan.with_events()?.accounts;
+    let mut history = plan.history(DateStream::yearly(Some(plan.start_date())).take(years))?
+        .with_journal();
+    for _ in history.by_ref() {}
+    history.take_failure()?;
 
-    table.printstd();
+    let stdout = io::stdout();
+    journal::write_journal(&mut stdout.lock(), plan.start_date(), &opening, history.journal())
+}
+
+fn print_import(plan: &Plan,
+                statement: Statement,
+                balances: bool,
+                suggest: bool,
+                output: &mut Output)
+                -> Result<()> {
+    // these are for pasting into the plan, so they are always yaml
+    if balances {
+        let accounts = statement.apply(&plan.accounts, plan.start_date())?;
+        write!(output.writer, "{}", serde_yaml::to_string(&accounts)?)?;
+        return Ok(());
+    }
+    if suggest {
+        let rules = recurring::suggest_rules(&statement.rows, today());
+        write!(output.writer, "{}", serde_yaml::to_string(&rules)?)?;
+        return Ok(());
+    }
+
+    output.report(&reports::statement(&statement))?;
+    if output.format.is_readable() {
+        for &(ref account, ref balance) in &statement.balances {
+            writeln!(output.writer, "{} ending balance: {}", account, balance)?;
+        }
+    }
     Ok(())
 }
 
+fn print_progress(plan: &Plan,
+                  actuals: &[Actual],
+                  years: usize,
+                  output: &mut Output)
+                  -> Result<()> {
+    let mut report =
+        Report::new(vec!["Date", "Account", "Projected", "Actual", "Variance", "On Track"]);
+    for variance in progress::variances(plan, actuals)? {
+        report.add_row(vec![Field::from(variance.date),
+                            Field::from(variance.account.as_str()),
+                            Field::from(variance.projected.clone()),
+                            Field::from(variance.actual.clone()),
+                            Field::from(variance.difference()),
+                            Field::from(variance.percent().map(Field::Percent))]);
+    }
+    output.report(&report)?;
+
+    // the forecast has different columns, so other programs only get the variances
+    if !output.format.is_readable() {
+        return Ok(());
+    }
+    if let Some(latest) = actuals.iter().max_by_key(|actual| actual.date) {
+        writeln!(output.writer, "\nRe-forecast from {}:", latest.date)?;
+        print_forecast(&progress::reforecast(plan, latest)?, years, false, output)?;
+    }
+    Ok(())
+}
+
+fn print_snapshots(store: &SnapshotStore, output: &mut Output) -> Result<()> {
+    let mut report = Report::new(vec!["Date", "Assets", "Liabilities", "Net Worth"]);
+    for snapshot in store.all()? {
+        let assets = snapshot.accounts.balance("assets").unwrap_or_else(|_| Money::zero());
+        let liabilities =
+            snapshot.accounts.balance("liabilities").unwrap_or_else(|_| Money::zero());
+        report.add_row(vec![Field::from(snapshot.date),
+                            Field::from(assets.clone()),
+                            Field::from(liabilities.clone()),
+                            Field::from(assets - liabilities)]);
+    }
+    output.report(&report)
+}
+
+fn print_goals(plan: &Plan, years: usize, output: &mut Output) -> Result<()> {
+    let readable = output.format.is_readable();
+    output.report(&reports::goals(plan, years, readable)?)
+}
+
+fn print_solution(plan: &Plan,
+                  rule: &str,
+                  condition: &str,
+                  by: NaiveDate,
+                  low: Option<f64>,
+                  high: Option<f64>)
+                  -> Result<()> {
+    let condition: Condition = condition.parse()?;
+
+    let (name, current) = match plan.rules.get(rule) {
+        Some(r) => (r.parameter_name(), r.parameter()),
+        None => bail!(ErrorKind::UnknownRule(String::from(rule))),
+    };
+    let (name, current) = match (name, current) {
+        (Some(name), Some(current)) => (name, current),
+        _ => bail!(ErrorKind::NoParameter(String::from(rule))),
+    };
+
+    let low = low.unwrap_or(0.0);
+    let high = high.unwrap_or(if current > 0.0 { current * 100.0 } else { 1000000.0 });
+    let solution = solve::solve(plan, rule, &condition, by, low, high)?;
+
+    if name.ends_with("rate") {
+        println!("'{}' {} = {:.4}", rule, name, solution.value);
+    } else {
+        println!("'{}' {} = {}", rule, name, Money::from(solution.value));
+    }
+    println!("{} on {} would be {}",
+             condition.expr,
+             by,
+             Money::from(solution.result));
+    Ok(())
+}
+
+fn print_sensitivity(plan: &Plan,
+                     expr: &str,
+                     date: NaiveDate,
+                     variations: &[Variation],
+                     output: &mut Output)
+                     -> Result<()> {
+    let expr: Expr = expr.parse()?;
+    let grid = sensitivity::sensitivity(plan, &variations[0], variations.get(1), &expr, date)?;
+
+    let mut header = Vec::new();
+    header.push(match variations.get(1) {
+        Some(columns) => format!("{} \\ {}", variations[0].rule, columns.rule),
+        None => variations[0].rule.clone(),
+    });
+    for column in &grid.columns {
+        header.push(if variations.len() > 1 {
+            format!("{:+}", column)
+        } else {
+            format!("{} on {}", expr, date)
+        });
+    }
+    let mut report = Report::new(header);
+
+    for (row, values) in grid.rows.iter().zip(grid.values.iter()) {
+        let mut result = Vec::new();
+        result.push(Field::from(format!("{:+}", row)));
+        for value in values {
+            result.push(Field::from(value.clone()));
+        }
+        report.add_row(result);
+    }
+
+    output.report(&report)
+}
+
+fn print_compare(plan: &Plan,
+                 scenarios: Vec<String>,
+                 accounts: Vec<String>,
+                 years: usize,
+                 output: &mut Output)
+                 -> Result<()> {
+    let mut names = scenarios;
+    if names.is_empty() {
+        names = plan.scenarios.keys().cloned().collect();
+        names.sort();
+    }
+    let account_names = if accounts.is_empty() {
+        plan.with_events()?.accounts.get_account_names()
+    } else {
+        accounts
+    };
+
+    let dates = || DateStream::yearly(Some(plan.start_date())).take(years);
+    let mut histories = Vec::new();
+    histories.push(plan.moments(dates())?);
+    for name in &names {
+        histories.push(plan.scenario(name)?.moments(dates())?);
+    }
+
+    let mut header = vec![String::from("Date"), String::from("Account"), String::from("Plan")];
+    for name in &names {
+        header.push(name.clone());
+        header.push(format!("{} - Plan", name));
+    }
+    let mut report = Report::new(header);
+
+    for (index, &(date, ref base)) in histories[0].iter().enumerate() {
+        for account in &account_names {
+            let base_balance = base.balance(account).unwrap_or_else(|_| Money::zero());
+
+            let mut result = vec![Field::from(date),
+                                  Field::from(account.as_str()),
+                                  Field::from(base_balance.clone())];
+            for history in &histories[1..] {
+                let balance = history[index]
+                    .1
+                    .balance(account)
+                    .unwrap_or_else(|_| Money::zero());
+                let difference = balance.clone() - base_balance.clone();
+                result.push(Field::from(balance));
+                result.push(Field::from(difference));
+            }
+            report.add_row(result);
+        }
+    }
+
+    output.report(&report)
+}
+
+fn read_file(path: &str) -> Result<String> {
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+fn read_statement(profile: &ImportProfile, file: &str, format: &str) -> Result<Statement> {
+    match format {
+        "ofx" => ofx::read(&read_file(file)?, profile),
+        "qif" => qif::read(&read_file(file)?, profile),
+        _ => profile.read_csv(file),
+    }
+}
+
+// the kind of export goes by its extension
+fn file_format(file: &str) -> &'static str {
+    match file.rsplit('.').next().map(|e| e.to_lowercase()) {
+        Some(ref extension) if extension == "ofx" || extension == "qfx" => "ofx",
+        Some(ref extension) if extension == "qif" => "qif",
+        _ => "csv",
+    }
+}
+
+fn format_arg() -> Arg<'static, 'static> {
+    Arg::with_name("format")
+        .long("format")
+        .value_name("FORMAT")
+        .help("Sets the format of the report, 'table' by default.")
+        .possible_values(&output::FORMATS)
+        .takes_value(true)
+}
+
+fn output_arg() -> Arg<'static, 'static> {
+    Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .value_name("FILE")
+        .help("Writes the report to a file instead.")
+        .takes_value(true)
+}
+
+fn output(matches: &ArgMatches) -> Result<Output> {
+    let format = value_t!(matches, "format", Format).unwrap_or(Format::Table);
+    Output::new(format, matches.value_of("output"))
+}
+
+fn import_command(name: &'static str, about: &'static str) -> App<'static, 'static> {
+    SubCommand::with_name(name)
+        .about(about)
+        .arg(Arg::with_name("file")
+            .help("Sets the file to import.")
+            .required(true)
+            .index(1))
+        .arg(Arg::with_name("profile")
+            .short("p")
+            .long("profile")
+            .value_name("PROFILE")
+            .help("Sets the import profile to use.")
+            .required(true)
+            .takes_value(true))
+        .arg(Arg::with_name("balances")
+            .long("balances")
+            .help("Prints the accounts with the statement applied."))
+        .arg(Arg::with_name("suggest")
+            .long("suggest")
+            .help("Prints rules for the payments that repeat, like suggest-rules.")
+            .conflicts_with("balances"))
+        .arg(format_arg())
+        .arg(output_arg())
+}
+
 fn run() -> Result<()> {
     env_logger::init()?;
 
@@ -77,6 +497,227 @@ fn run() -> Result<()> {
             .takes_value(true))
         .subcommand(SubCommand::with_name("forecast")
             .about("Calculate Asset values over <n> years.")
+            .arg(format_arg())
+            .arg(output_arg())
+            .arg(Arg::with_name("flows")
+                .long("flows")
+                .help("Adds the money that went in and out of every account to each row."))
+            .arg(Arg::with_name("chart")
+                .long("chart")
+                .value_name("FILE")
+                .help("Draws the balances as an SVG chart in a file instead.")
+                .takes_value(true))
+            .arg(Arg::with_name("style")
+                .long("style")
+                .value_name("STYLE")
+                .help("Sets the kind of chart, 'line' by default.")
+                .possible_values(&chart::STYLES)
+                .takes_value(true))
+            .arg(Arg::with_name("sparklines")
+                .long("sparklines")
+                .help("Draws the balances as sparklines in the terminal instead."))
+            .arg(Arg::with_name("account")
+                .short("a")
+                .long("account")
+                .value_name("ACCOUNT")
+                .help("Sets an account (or all the accounts under it) to chart.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1))
+            .arg(Arg::with_name("years")
+                .help("Sets the number of years to calculate forward.")
+                .index(1)))
+        .subcommand(SubCommand::with_name("compare")
+            .about("Compare the plan with its scenarios over <n> years.")
+            .arg(format_arg())
+            .arg(output_arg())
+            .arg(Arg::with_name("years")
+                .help("Sets the number of years to calculate forward.")
+                .index(1))
+            .arg(Arg::with_name("scenario")
+                .short("s")
+                .long("scenario")
+                .value_name("SCENARIO")
+                .help("Sets a scenario to compare, all of them are compared by default.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1))
+            .arg(Arg::with_name("account")
+                .short("a")
+                .long("account")
+                .value_name("ACCOUNT")
+                .help("Sets an account to compare, all of them are compared by default.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)))
+        .subcommand(SubCommand::with_name("warnings")
+            .about("List warnings from running the plan over <n> years.")
+            .arg(format_arg())
+            .arg(output_arg())
+            .arg(Arg::with_name("years")
+                .help("Sets the number of years to calculate forward.")
+                .index(1)))
+        .subcommand(SubCommand::with_name("gains")
+            .about("Report realized and unrealized gains over <n> years.")
+            .arg(format_arg())
+            .arg(output_arg())
+            .arg(Arg::with_name("years")
+                .help("Sets the number of years to calculate forward.")
+                .index(1)))
+        .subcommand(SubCommand::with_name("goals")
+            .about("Report progress towards the goals over <n> years.")
+            .arg(format_arg())
+            .arg(output_arg())
+            .arg(Arg::with_name("years")
+                .help("Sets the number of years to calculate forward.")
+                .index(1)))
+        .subcommand(SubCommand::with_name("import")
+            .about("Import transactions exported from a bank.")
+            .subcommand(import_command("csv", "Import a CSV export using a profile."))
+            .subcommand(import_command("ofx", "Import an OFX or QFX statement using a profile."))
+            .subcommand(import_command("qif", "Import a QIF export using a profile.")))
+        .subcommand(SubCommand::with_name("journal")
+            .about("Write the transactions of the next <n> years as a ledger journal.")
+            .arg(Arg::with_name("years")
+                .help("Sets the number of years to calculate forward.")
+                .index(1)))
+        .subcommand(SubCommand::with_name("limits")
+            .about("Report every time an account hit its limits over <n> years.")
+            .arg(format_arg())
+            .arg(output_arg())
+            .arg(Arg::with_name("years")
+                .help("Sets the number of years to calculate forward.")
+                .index(1)))
+        .subcommand(SubCommand::with_name("sensitivity")
+            .about("Show how an expression on a date changes as rule parameters change.")
+            .arg(format_arg())
+            .arg(output_arg())
+            .arg(Arg::with_name("expression")
+                .help("Sets the expression to calculate, like 'assets - liabilities'.")
+                .required(true)
+                .index(1))
+            .arg(Arg::with_name("date")
+                .help("Sets the date to calculate the expression on.")
+                .required(true)
+                .index(2))
+            .arg(Arg::with_name("vary")
+                .long("vary")
+                .value_name("RULE:LOW:HIGH:STEPS")
+                .help("Changes the parameter of a rule from LOW to HIGH in STEPS steps. Can be \
+                       used twice.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .max_values(2)
+                .required(true)))
+        .subcommand(SubCommand::with_name("suggest-rules")
+            .about("Suggest rules for the payments that repeat in a CSV, OFX or QIF export.")
+            .arg(Arg::with_name("file")
+                .help("Sets the file to look through.")
+                .required(true)
+                .index(1))
+            .arg(Arg::with_name("profile")
+                .short("p")
+                .long("profile")
+                .value_name("PROFILE")
+                .help("Sets the import profile to use.")
+                .required(true)
+                .takes_value(true)))
+        .subcommand(SubCommand::with_name("snapshot")
+            .about("Keep the balances of the accounts on a day in a snapshot store.")
+            .arg(Arg::with_name("store")
+                .short("s")
+                .long("store")
+                .value_name("DIR")
+                .help("Sets the directory of the snapshot store, 'snapshots' by default.")
+                .takes_value(true))
+            .subcommand(SubCommand::with_name("add")
+                .about("Add a snapshot of the plan's accounts, or of them with an import.")
+                .arg(Arg::with_name("date")
+                    .short("d")
+                    .long("date")
+                    .value_name("DATE")
+                    .help("Sets the day of the snapshot, today by default.")
+                    .takes_value(true))
+                .arg(Arg::with_name("import")
+                    .long("import")
+                    .value_name("FILE")
+                    .help("Sets a CSV, OFX or QIF export to apply to the accounts.")
+                    .requires("profile")
+                    .takes_value(true))
+                .arg(Arg::with_name("profile")
+                    .short("p")
+                    .long("profile")
+                    .value_name("PROFILE")
+                    .help("Sets the import profile to use.")
+                    .takes_value(true)))
+            .subcommand(SubCommand::with_name("list")
+                .about("List the snapshots.")
+                .arg(format_arg())
+                .arg(output_arg()))
+            .subcommand(SubCommand::with_name("show")
+                .about("Show the accounts in a snapshot.")
+                .arg(Arg::with_name("date")
+                    .help("Sets the day of the snapshot.")
+                    .required(true)
+                    .index(1))))
+        .subcommand(SubCommand::with_name("solve")
+            .about("Find the parameter of a rule that meets a condition by a date.")
+            .arg(Arg::with_name("rule")
+                .help("Sets the name of the rule to change.")
+                .required(true)
+                .index(1))
+            .arg(Arg::with_name("condition")
+                .help("Sets the condition to meet, like 'assets:rrsp >= 500000'.")
+                .required(true)
+                .index(2))
+            .arg(Arg::with_name("by")
+                .help("Sets the date to meet the condition by.")
+                .required(true)
+                .index(3))
+            .arg(Arg::with_name("low")
+                .long("low")
+                .value_name("LOW")
+                .help("Sets the lowest value to try.")
+                .takes_value(true))
+            .arg(Arg::with_name("high")
+                .long("high")
+                .value_name("HIGH")
+                .help("Sets the highest value to try.")
+                .takes_value(true)))
+        .subcommand(SubCommand::with_name("progress")
+            .about("Compare actual balances with the plan, and forecast <n> years from them.")
+            .arg(format_arg())
+            .arg(output_arg())
+            .arg(Arg::with_name("years")
+                .help("Sets the number of years to calculate forward.")
+                .index(1))
+            .arg(Arg::with_name("actuals")
+                .short("a")
+                .long("actuals")
+                .value_name("FILE")
+                .help("Adds the actual balances in a file to the plan's.")
+                .takes_value(true))
+            .arg(Arg::with_name("snapshots")
+                .long("snapshots")
+                .value_name("DIR")
+                .help("Adds the snapshots in a snapshot store to the plan's actuals.")
+                .takes_value(true)))
+        .subcommand(SubCommand::with_name("report")
+            .about("Write a report on the plan over <n> years as a single HTML page.")
+            .arg(Arg::with_name("html")
+                .long("html")
+                .value_name("FILE")
+                .help("Sets the file to write the page to.")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("years")
+                .help("Sets the number of years to calculate forward.")
+                .index(1)))
+        .subcommand(SubCommand::with_name("room")
+            .about("Report unused contribution room over <n> years.")
+            .arg(format_arg())
+            .arg(output_arg())
             .arg(Arg::with_name("years")
                 .help("Sets the number of years to calculate forward.")
                 .index(1)))
@@ -84,11 +725,157 @@ fn run() -> Result<()> {
 
     let input_file = File::open(matches.value_of("input").unwrap_or("input.yaml"))?;
     let plan: Plan = serde_yaml::from_reader(input_file)?;
-    plan.accounts.validate()?;
+    plan.validate()?;
 
     if let Some(matches) = matches.subcommand_matches("forecast") {
         let years = value_t!(matches, "years", usize).unwrap_or(25);
-        print_forecast(&plan, years)?;
+        if matches.is_present("chart") || matches.is_present("sparklines") {
+            let accounts: Vec<String> = matches.values_of("account")
+                .map(|values| values.map(String::from).collect())
+                .unwrap_or_else(Vec::new);
+            print_chart(&plan,
+                        years,
+                        &accounts,
+                        matches.value_of("chart"),
+                        value_t!(matches, "style", ChartStyle).unwrap_or(ChartStyle::Line),
+                        matches.is_present("sparklines"))?;
+        } else {
+            print_forecast(&plan, years, matches.is_present("flows"), &mut output(matches)?)?;
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("compare") {
+        let years = value_t!(matches, "years", usize).unwrap_or(25);
+        let values = |name: &str| -> Vec<String> {
+            matches.values_of(name)
+                .map(|values| values.map(String::from).collect())
+                .unwrap_or_else(Vec::new)
+        };
+        print_compare(&plan,
+                      values("scenario"),
+                      values("account"),
+                      years,
+                      &mut output(matches)?)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("warnings") {
+        let years = value_t!(matches, "years", usize).unwrap_or(25);
+        print_warnings(&plan, years, &mut output(matches)?)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("gains") {
+        let years = value_t!(matches, "years", usize).unwrap_or(25);
+        print_gains(&plan, years, &mut output(matches)?)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("goals") {
+        let years = value_t!(matches, "years", usize).unwrap_or(25);
+        print_goals(&plan, years, &mut output(matches)?)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("import") {
+        if let (format, Some(matches)) = matches.subcommand() {
+            let profile = plan.import_profile(matches.value_of("profile").unwrap())?;
+            let statement = read_statement(profile, matches.value_of("file").unwrap(), format)?;
+            print_import(&plan,
+                         statement,
+                         matches.is_present("balances"),
+                         matches.is_present("suggest"),
+                         &mut output(matches)?)?;
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("suggest-rules") {
+        let profile = plan.import_profile(matches.value_of("profile").unwrap())?;
+        let file = matches.value_of("file").unwrap();
+        let statement = read_statement(profile, file, file_format(file))?;
+        let rules = recurring::suggest_rules(&statement.rows, today());
+        print!("{}", serde_yaml::to_string(&rules)?);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("snapshot") {
+        let store = SnapshotStore::new(matches.value_of("store").unwrap_or("snapshots"));
+        if let Some(matches) = matches.subcommand_matches("add") {
+            let date = match matches.value_of("date") {
+                Some(_) => value_t!(matches, "date", NaiveDate).unwrap_or_else(|e| e.exit()),
+                None => today(),
+            };
+            let accounts = match matches.value_of("import") {
+                Some(file) => {
+                    let profile = plan.import_profile(matches.value_of("profile").unwrap())?;
+                    let statement = read_statement(profile, file, file_format(file))?;
+                    statement.apply(&plan.accounts, plan.start_date())?
+                }
+                None => plan.accounts.clone(),
+            };
+            store.add(&Actual {
+                    date: date,
+                    accounts: accounts,
+                })?;
+        }
+        if let Some(matches) = matches.subcommand_matches("list") {
+            print_snapshots(&store, &mut output(matches)?)?;
+        }
+        if let Some(matches) = matches.subcommand_matches("show") {
+            let date = value_t!(matches, "date", NaiveDate).unwrap_or_else(|e| e.exit());
+            print!("{}", serde_yaml::to_string(&store.get(date)?.accounts)?);
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("journal") {
+        let years = value_t!(matches, "years", usize).unwrap_or(25);
+        print_journal(&plan, years)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("limits") {
+        let years = value_t!(matches, "years", usize).unwrap_or(25);
+        print_limits(&plan, years, &mut output(matches)?)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("sensitivity") {
+        let date = value_t!(matches, "date", NaiveDate).unwrap_or_else(|e| e.exit());
+        let variations = values_t!(matches, "vary", Variation).unwrap_or_else(|e| e.exit());
+        print_sensitivity(&plan,
+                          matches.value_of("expression").unwrap(),
+                          date,
+                          &variations,
+                          &mut output(matches)?)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("solve") {
+        let by = value_t!(matches, "by", NaiveDate).unwrap_or_else(|e| e.exit());
+        let low = value_t!(matches, "low", f64).ok();
+        let high = value_t!(matches, "high", f64).ok();
+        print_solution(&plan,
+                       matches.value_of("rule").unwrap(),
+                       matches.value_of("condition").unwrap(),
+                       by,
+                       low,
+                       high)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("progress") {
+        let years = value_t!(matches, "years", usize).unwrap_or(25);
+        let mut actuals = plan.actuals.clone();
+        if let Some(file) = matches.value_of("actuals") {
+            let more: Vec<Actual> = serde_yaml::from_reader(File::open(file)?)?;
+            actuals.extend(more);
+        }
+        if let Some(dir) = matches.value_of("snapshots") {
+            actuals.extend(SnapshotStore::new(dir).all()?);
+        }
+        print_progress(&plan, &actuals, years, &mut output(matches)?)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("report") {
+        let years = value_t!(matches, "years", usize).unwrap_or(25);
+        let page = html::report(&plan, years)?;
+        File::create(matches.value_of("html").unwrap())?.write_all(page.as_bytes())?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("room") {
+        let years = value_t!(matches, "years", usize).unwrap_or(25);
+        print_room(&plan, years, &mut output(matches)?)?;
     }
 
     Ok(())
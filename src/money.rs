@@ -21,6 +21,18 @@ impl Money {
     pub fn mul_percent(self, percentage: Float) -> Money {
         Money { float: self.float * percentage }
     }
+
+    pub fn to_f64(&self) -> f64 {
+        self.float.to_f64()
+    }
+
+    pub fn min(self, other: Money) -> Money {
+        if other < self { other } else { self }
+    }
+
+    pub fn max(self, other: Money) -> Money {
+        if other > self { other } else { self }
+    }
 }
 
 impl From<f32> for Money {
@@ -0,0 +1,210 @@
+use chrono::prelude::*;
+
+use errors::*;
+use imports::*;
+
+// a statement transaction, filled in as its elements are read
+#[derive(Default)]
+struct StatementTransaction {
+    posted: Option<String>,
+    amount: Option<String>,
+    name: Option<String>,
+    memo: Option<String>,
+    line: usize,
+}
+
+// the elements of the document in order, as (line, tag, text). closing tags start with a /.
+// the older sgml files leave the elements with text in them open, so the text is everything up
+// to the next tag either way.
+fn elements(contents: &str) -> Vec<(usize, &str, String)> {
+    let mut elements = Vec::new();
+    let mut line = 1;
+    let mut rest = match contents.find('<') {
+        // the sgml header before the first tag is a list of 'NAME:VALUE' lines
+        Some(start) => {
+            line += contents[..start].matches('\n').count();
+            &contents[start..]
+        }
+        None => return elements,
+    };
+
+    while let Some(end) = rest.find('>') {
+        let tag = rest[1..end].trim();
+        let after = &rest[end + 1..];
+        let next = after.find('<').unwrap_or_else(|| after.len());
+        let text = unescape(after[..next].trim());
+        // xml declarations and processing instructions aren't part of the statement
+        if !tag.starts_with('?') && !tag.starts_with('!') {
+            elements.push((line, tag, text));
+        }
+        line += rest[..end + 1 + next].matches('\n').count();
+        rest = &after[next..];
+    }
+    elements
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+// dates look like 20170131, with the time and time zone after it left off here
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    if text.len() < 8 {
+        return None;
+    }
+    NaiveDate::parse_from_str(&text[..8], "%Y%m%d").ok()
+}
+
+impl StatementTransaction {
+    fn to_row(&self,
+              profile: &ImportProfile,
+              mappings: &[(::regex::Regex, String)])
+              -> Result<ImportedRow> {
+        let date = match self.posted.as_ref().and_then(|posted| parse_date(posted)) {
+            Some(date) => date,
+            None => bail!(ErrorKind::InvalidRow(self.line, String::from("date"))),
+        };
+        let amount = match self.amount.as_ref().and_then(|amount| amount.parse::<f64>().ok()) {
+            Some(amount) => amount,
+            None => bail!(ErrorKind::InvalidRow(self.line, String::from("amount"))),
+        };
+        let description = self.name.clone().or_else(|| self.memo.clone()).unwrap_or_default();
+        Ok(profile.row(date, description, amount, mappings))
+    }
+}
+
+// reads the transactions and the ledger balances out of an ofx (or qfx, which is the same) file.
+// each statement is for the account the profile has for its account id, or the profile's account.
+pub fn read(contents: &str, profile: &ImportProfile) -> Result<Statement> {
+    let mappings = profile.compiled_mappings()?;
+    let mut rows = Vec::new();
+    let mut balances = Vec::new();
+    let mut statement = profile.clone();
+    let mut transaction: Option<StatementTransaction> = None;
+    let mut in_balance = false;
+
+    for (line, tag, text) in elements(contents) {
+        match tag {
+            "STMTRS" | "CCSTMTRS" => statement.account = profile.account.clone(),
+            // transfers have the account id of the other account in the transaction
+            "ACCTID" if transaction.is_none() => {
+                if let Some(account) = profile.accounts.get(&text) {
+                    statement.account = account.clone();
+                }
+            }
+            "STMTTRN" => {
+                transaction = Some(StatementTransaction {
+                    line: line,
+                    ..StatementTransaction::default()
+                })
+            }
+            "/STMTTRN" => {
+                if let Some(finished) = transaction.take() {
+                    rows.push(finished.to_row(&statement, &mappings)?);
+                }
+            }
+            "LEDGERBAL" => in_balance = true,
+            "/LEDGERBAL" => in_balance = false,
+            "BALAMT" if in_balance => {
+                match text.parse::<f64>() {
+                    Ok(amount) => {
+                        balances.push((statement.account.clone(), statement.balance(amount)))
+                    }
+                    Err(_) => bail!(ErrorKind::InvalidRow(line, String::from("balance"))),
+                }
+            }
+            _ => {
+                if let Some(ref mut transaction) = transaction {
+                    let field = match tag {
+                        "DTPOSTED" => &mut transaction.posted,
+                        "TRNAMT" => &mut transaction.amount,
+                        "NAME" => &mut transaction.name,
+                        "MEMO" => &mut transaction.memo,
+                        _ => continue,
+                    };
+                    *field = Some(text);
+                }
+            }
+        }
+    }
+
+    Ok(Statement {
+        rows: rows,
+        balances: balances,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use money::Money;
+
+    #[test]
+    fn test_sgml_statement() {
+        let ofx = "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX>\n<BANKMSGSRSV1><STMTTRNRS><STMTRS>\n\
+                   <BANKTRANLIST>\n\
+                   <STMTTRN>\n<TRNTYPE>DEBIT\n<DTPOSTED>20170105120000[-5:EST]\n\
+                   <TRNAMT>-42.50\n<NAME>GROCERY &amp; CO\n</STMTTRN>\n\
+                   </BANKTRANLIST>\n\
+                   <LEDGERBAL><BALAMT>1234.56<DTASOF>20170131</LEDGERBAL>\n\
+                   </STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>\n";
+        let profile: ImportProfile =
+            ::serde_yaml::from_str("account: assets:chequing\nmappings:\n  \
+                                      - pattern: GROCERY\n    account: expenses:food")
+                .unwrap();
+        let statement = read(ofx, &profile).unwrap();
+
+        assert_eq!(statement.rows.len(), 1);
+        assert_eq!(statement.rows[0].description, "GROCERY & CO");
+        let transaction = &statement.rows[0].transaction;
+        assert_eq!(transaction.date, NaiveDate::from_ymd(2017, 1, 5));
+        assert_eq!(transaction.from, "assets:chequing");
+        assert_eq!(transaction.to, "expenses:food");
+        assert_eq!(statement.balances,
+                   vec![(String::from("assets:chequing"), Money::from(1234.56))]);
+    }
+
+    #[test]
+    fn test_xml_statements_by_account_id() {
+        let ofx = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                   <?OFX OFXHEADER=\"200\" VERSION=\"220\"?>\n\
+                   <OFX><BANKMSGSRSV1>\n\
+                   <STMTTRNRS><STMTRS>\n\
+                   <BANKACCTFROM><ACCTID>1111</ACCTID></BANKACCTFROM>\n\
+                   <BANKTRANLIST><STMTTRN>\n\
+                   <DTPOSTED>20170105</DTPOSTED><TRNAMT>-42.50</TRNAMT>\n\
+                   <NAME>Grocer</NAME>\n\
+                   </STMTTRN></BANKTRANLIST>\n\
+                   <LEDGERBAL><BALAMT>1000.00</BALAMT><DTASOF>20170131</DTASOF></LEDGERBAL>\n\
+                   </STMTRS></STMTTRNRS>\n\
+                   <STMTTRNRS><STMTRS>\n\
+                   <BANKACCTFROM><ACCTID>2222</ACCTID></BANKACCTFROM>\n\
+                   <BANKTRANLIST><STMTTRN>\n\
+                   <DTPOSTED>20170110</DTPOSTED><TRNAMT>5.25</TRNAMT>\n\
+                   <MEMO>Interest</MEMO>\n\
+                   <BANKACCTTO><ACCTID>9999</ACCTID></BANKACCTTO>\n\
+                   </STMTTRN></BANKTRANLIST>\n\
+                   <LEDGERBAL><BALAMT>5005.25</BALAMT><DTASOF>20170131</DTASOF></LEDGERBAL>\n\
+                   </STMTRS></STMTTRNRS>\n\
+                   </BANKMSGSRSV1></OFX>\n";
+        let profile: ImportProfile = ::serde_yaml::from_str("account: assets:chequing\n\
+                                                             accounts:\n  \
+                                                               '2222': assets:savings")
+            .unwrap();
+        let statement = read(ofx, &profile).unwrap();
+
+        assert_eq!(statement.rows.len(), 2);
+        assert_eq!(statement.rows[0].transaction.from, "assets:chequing");
+        assert_eq!(statement.rows[1].description, "Interest");
+        assert_eq!(statement.rows[1].transaction.to, "assets:savings");
+        assert_eq!(statement.rows[1].transaction.date, NaiveDate::from_ymd(2017, 1, 10));
+        assert_eq!(statement.balances,
+                   vec![(String::from("assets:chequing"), Money::from(1000)),
+                        (String::from("assets:savings"), Money::from(5005.25))]);
+    }
+}
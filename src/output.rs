@@ -0,0 +1,356 @@
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::str::FromStr;
+use chrono::prelude::*;
+use prettytable::Table;
+use prettytable::row::Row;
+use prettytable::cell::Cell;
+use serde::{Serialize, Serializer};
+use serde::ser::SerializeMap;
+use serde_json;
+use serde_yaml;
+
+use money::Money;
+use errors::*;
+
+pub const FORMATS: [&'static str; 5] = ["table", "csv", "json", "yaml", "markdown"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Table,
+    Csv,
+    Json,
+    Yaml,
+    Markdown,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Format> {
+        match s {
+            "table" => Ok(Format::Table),
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            "yaml" => Ok(Format::Yaml),
+            "markdown" => Ok(Format::Markdown),
+            _ => Err(ErrorKind::UnknownFormat(String::from(s)).into()),
+        }
+    }
+}
+
+impl Format {
+    // formats for reading, rather than for other programs
+    pub fn is_readable(&self) -> bool {
+        *self == Format::Table || *self == Format::Markdown
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Field {
+    Text(String),
+    Date(NaiveDate),
+    Money(Money),
+    Integer(i64),
+    Percent(f64),
+    Empty,
+}
+
+impl From<String> for Field {
+    fn from(text: String) -> Field {
+        Field::Text(text)
+    }
+}
+
+impl<'a> From<&'a str> for Field {
+    fn from(text: &'a str) -> Field {
+        Field::Text(String::from(text))
+    }
+}
+
+impl From<NaiveDate> for Field {
+    fn from(date: NaiveDate) -> Field {
+        Field::Date(date)
+    }
+}
+
+impl From<Money> for Field {
+    fn from(money: Money) -> Field {
+        Field::Money(money)
+    }
+}
+
+impl From<i32> for Field {
+    fn from(integer: i32) -> Field {
+        Field::Integer(integer as i64)
+    }
+}
+
+impl<T: Into<Field>> From<Option<T>> for Field {
+    fn from(value: Option<T>) -> Field {
+        value.map_or(Field::Empty, Into::into)
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Field::Text(ref text) => write!(f, "{}", text),
+            Field::Date(ref date) => write!(f, "{}", date),
+            Field::Money(ref money) => write!(f, "{}", money),
+            Field::Integer(integer) => write!(f, "{}", integer),
+            Field::Percent(percent) => write!(f, "{:+.1}%", percent),
+            Field::Empty => write!(f, "-"),
+        }
+    }
+}
+
+// other programs get plain numbers, to the cent
+impl Serialize for Field {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        match *self {
+            Field::Text(ref text) => serializer.serialize_str(text),
+            Field::Date(ref date) => serializer.serialize_str(&date.to_string()),
+            Field::Money(ref money) => {
+                serializer.serialize_f64((money.to_f64() * 100.0).round() / 100.0)
+            }
+            Field::Integer(integer) => serializer.serialize_i64(integer),
+            Field::Percent(percent) => serializer.serialize_f64((percent * 10.0).round() / 10.0),
+            Field::Empty => serializer.serialize_none(),
+        }
+    }
+}
+
+// text that can go in html (or svg) as it is
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// the name of a column for other programs, like 'over_by' for 'Over By'
+fn key(column: &str) -> String {
+    column.to_lowercase().split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+struct Record<'a> {
+    keys: &'a [String],
+    fields: &'a [Field],
+}
+
+// a record is an object with a key for every column, in the same order as the columns
+impl<'a> Serialize for Record<'a> {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let mut map = serializer.serialize_map(Some(self.keys.len()))?;
+        for (key, field) in self.keys.iter().zip(self.fields.iter()) {
+            map.serialize_key(key)?;
+            map.serialize_value(field)?;
+        }
+        map.end()
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        String::from(text)
+    }
+}
+
+// a report is a table that can be written out in any of the formats
+pub struct Report {
+    columns: Vec<String>,
+    rows: Vec<Vec<Field>>,
+}
+
+impl Report {
+    pub fn new<S: Into<String>>(columns: Vec<S>) -> Report {
+        Report {
+            columns: columns.into_iter().map(Into::into).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn add_row(&mut self, row: Vec<Field>) {
+        self.rows.push(row);
+    }
+
+    // the report as an html table, for putting in a page
+    pub fn html(&self) -> String {
+        let cells = |tag: &str, fields: Vec<String>| {
+            fields.iter()
+                .map(|field| format!("<{}>{}</{}>", tag, escape(field), tag))
+                .collect::<Vec<_>>()
+                .join("")
+        };
+        let mut html = String::from("<table>\n");
+        html.push_str(&format!("<tr>{}</tr>\n", cells("th", self.columns.clone())));
+        for row in &self.rows {
+            let fields = row.iter().map(|field| field.to_string()).collect();
+            html.push_str(&format!("<tr>{}</tr>\n", cells("td", fields)));
+        }
+        html.push_str("</table>\n");
+        html
+    }
+
+    pub fn write(&self, format: Format, out: &mut Write) -> Result<()> {
+        let keys: Vec<String> = self.columns.iter().map(|column| key(column)).collect();
+        let records: Vec<Record> = self.rows
+            .iter()
+            .map(|row| {
+                Record {
+                    keys: &keys,
+                    fields: row,
+                }
+            })
+            .collect();
+
+        match format {
+            Format::Table => {
+                let mut table = Table::new();
+                table.add_row(Row::new(self.columns.iter().map(|c| Cell::new(c)).collect()));
+                for row in &self.rows {
+                    table.add_row(Row::new(row.iter()
+                        .map(|field| Cell::new(&field.to_string()))
+                        .collect()));
+                }
+                write!(out, "{}", table)?;
+            }
+            Format::Markdown => {
+                let line = |fields: Vec<String>| {
+                    let fields: Vec<String> =
+                        fields.iter().map(|field| field.replace('|', "\\|")).collect();
+                    format!("| {} |", fields.join(" | "))
+                };
+                let rule = self.columns.iter().map(|_| String::from("---")).collect();
+                writeln!(out, "{}", line(self.columns.clone()))?;
+                writeln!(out, "{}", line(rule))?;
+                for row in &self.rows {
+                    writeln!(out, "{}", line(row.iter().map(|field| field.to_string()).collect()))?;
+                }
+            }
+            Format::Csv => {
+                let header: Vec<String> = keys.iter().map(|key| csv_field(key)).collect();
+                writeln!(out, "{}", header.join(","))?;
+                for row in &self.rows {
+                    let fields: Vec<String> = row.iter()
+                        .map(|field| {
+                            match *field {
+                                Field::Money(ref money) => format!("{:.2}", money.to_f64()),
+                                Field::Percent(percent) => format!("{:.1}", percent),
+                                Field::Empty => String::new(),
+                                ref field => csv_field(&field.to_string()),
+                            }
+                        })
+                        .collect();
+                    writeln!(out, "{}", fields.join(","))?;
+                }
+            }
+            Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&records)?)?,
+            Format::Yaml => write!(out, "{}", serde_yaml::to_string(&records)?)?,
+        }
+        Ok(())
+    }
+}
+
+// where reports go, stdout unless there's a file
+pub struct Output {
+    pub format: Format,
+    pub writer: Box<Write>,
+}
+
+impl Output {
+    pub fn new(format: Format, path: Option<&str>) -> Result<Output> {
+        let writer: Box<Write> = match path {
+            Some(path) => Box::new(File::create(path)?),
+            None => Box::new(io::stdout()),
+        };
+        Ok(Output {
+            format: format,
+            writer: writer,
+        })
+    }
+
+    pub fn report(&mut self, report: &Report) -> Result<()> {
+        report.write(self.format, &mut *self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Parsed {
+        date: String,
+        account: String,
+        over_by: Option<f64>,
+        on_track: Option<f64>,
+    }
+
+    fn report() -> Report {
+        let mut report = Report::new(vec!["Date", "Account", "Over By", "On Track"]);
+        report.add_row(vec![Field::from(NaiveDate::from_ymd(2017, 1, 1)),
+                            Field::from("assets:a|b"),
+                            Field::from(Money::from(12.3456)),
+                            Field::Percent(-12.34)]);
+        report.add_row(vec![Field::from(NaiveDate::from_ymd(2018, 1, 1)),
+                            Field::from("assets:c"),
+                            Field::Empty,
+                            Field::Empty]);
+        report
+    }
+
+    fn written(format: Format) -> String {
+        let mut out = Vec::new();
+        report().write(format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn check(rows: Vec<Parsed>) {
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].date, "2017-01-01");
+        assert_eq!(rows[0].account, "assets:a|b");
+        assert_eq!(rows[0].over_by, Some(12.35));
+        assert_eq!(rows[0].on_track, Some(-12.3));
+        assert_eq!(rows[1].account, "assets:c");
+        assert_eq!(rows[1].over_by, None);
+        assert_eq!(rows[1].on_track, None);
+    }
+
+    #[test]
+    fn test_json_report() {
+        check(::serde_json::from_str(&written(Format::Json)).unwrap());
+    }
+
+    #[test]
+    fn test_yaml_report() {
+        check(::serde_yaml::from_str(&written(Format::Yaml)).unwrap());
+    }
+
+    #[test]
+    fn test_markdown_report() {
+        assert_eq!(written(Format::Markdown),
+                   "| Date | Account | Over By | On Track |\n\
+                    | --- | --- | --- | --- |\n\
+                    | 2017-01-01 | assets:a\\|b | $12.35 | -12.3% |\n\
+                    | 2018-01-01 | assets:c | - | - |\n");
+    }
+
+    #[test]
+    fn test_csv_report() {
+        let mut report = Report::new(vec!["Date", "Account", "Over By"]);
+        report.add_row(vec![Field::from(NaiveDate::from_ymd(2017, 1, 1)),
+                            Field::from("assets:a, b"),
+                            Field::from(Money::from(12.5))]);
+        let mut out = Vec::new();
+        report.write(Format::Csv, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "date,account,over_by\n2017-01-01,\"assets:a, b\",12.50\n");
+    }
+}
@@ -6,11 +6,54 @@ use chrono;
 use money::Money;
 use accounts::*;
 use iterators::*;
+use strategy::Strategy;
+use debts::*;
+use waterfall::*;
+use tax::*;
+use withdrawal::*;
+use goals::Goal;
+use errors::*;
+use expression::Expr;
+use events::*;
+use rates::*;
+use accrual::Accrual;
+use expenses::*;
+use ledger::accounts_or_ledger;
+use imports::ImportProfile;
+use progress::Actual;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Plan {
+    #[serde(deserialize_with = "accounts_or_ledger")]
     pub accounts: Accounts,
     pub rules: HashMap<String, Rule>,
+    #[serde(default)]
+    pub tax: Option<TaxRules>,
+    #[serde(default)]
+    pub goals: HashMap<String, Goal>,
+    #[serde(default)]
+    pub scenarios: HashMap<String, Scenario>,
+    #[serde(default)]
+    pub events: HashMap<String, LifeEvent>,
+    #[serde(default)]
+    pub indices: HashMap<String, Vec<IndexRate>>,
+    #[serde(default)]
+    pub imports: HashMap<String, ImportProfile>,
+    // the day the balances in accounts are from, today by default
+    #[serde(default)]
+    pub start_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub actuals: Vec<Actual>,
+}
+
+// a scenario is a set of changes on top of the plan
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Scenario {
+    pub accounts: Option<Accounts>,
+    #[serde(default)]
+    pub rules: HashMap<String, Rule>,
+    #[serde(default)]
+    pub remove: Vec<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -18,6 +61,11 @@ pub struct Plan {
 pub enum Rule {
     RepeatingMoney(MoneyTransfer),
     CompoundingInterest(CompoundingInterest),
+    DebtPayoff(DebtPayoff),
+    Waterfall(Waterfall),
+    Withdrawal(Withdrawal),
+    Expense(Expense),
+    Fee(Fee),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -28,6 +76,8 @@ pub struct MoneyTransfer {
     pub frequency: Frequency,
     pub start_date: Option<NaiveDate>,
     pub end_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -37,6 +87,11 @@ pub struct CompoundingInterest {
     pub period: Frequency,
     pub start_date: Option<NaiveDate>,
     pub end_date: Option<NaiveDate>,
+    // changes to the interest rate over time, the interest rate applies until the first one
+    #[serde(default)]
+    pub schedule: Vec<ScheduledRate>,
+    // accrue interest every day and pay it every period, instead of on the balance on the day
+    pub accrual: Option<Accrual>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -47,7 +102,7 @@ pub enum Frequency {
     Once,
 }
 
-fn today() -> NaiveDate {
+pub fn today() -> NaiveDate {
     let local = Local::now();
     NaiveDate::from_ymd(local.year(), local.month(), local.day())
 }
@@ -57,37 +112,302 @@ impl Plan {
         let mut iters = Vec::new();
 
         for rule in self.rules.values() {
-            if let Rule::RepeatingMoney(ref t) = *rule {
-                iters.push(RepeatingTransaction::from(t.clone()));
+            match *rule {
+                Rule::RepeatingMoney(ref t) => iters.push(RepeatingTransaction::from(t.clone())),
+                Rule::Expense(ref e) => iters.push(RepeatingTransaction::from(e.clone())),
+                _ => {}
             }
         }
 
         SortedIterator::from_iter(iters.into_iter())
     }
 
-    fn compounding(&self) -> SortedIterator<CompoundedInterest, InterestStream> {
+    fn compounding(&self) -> Result<SortedIterator<CompoundedInterest, InterestStream>> {
         let mut iters = Vec::new();
 
+        for (name, rule) in &self.rules {
+            match *rule {
+                Rule::CompoundingInterest(ref c) => {
+                    iters.push(InterestStream::new(name, c.clone(), &self.indices)?)
+                }
+                Rule::Fee(ref f) => {
+                    iters.push(InterestStream::new(name,
+                                                   CompoundingInterest::from(f.clone()),
+                                                   &self.indices)?
+                        .paid_to(f.account()))
+                }
+                _ => {}
+            }
+        }
+
+        Ok(SortedIterator::from_iter(iters.into_iter()))
+    }
+
+    // every rule accrues on its own, even when there are two on the same account
+    fn accruals(&self) -> Vec<(String, String, Accrual)> {
+        let mut accruals = Vec::new();
+
+        for (name, rule) in &self.rules {
+            if let Rule::CompoundingInterest(CompoundingInterest { ref account,
+                                                                   accrual: Some(ref accrual),
+                                                                   .. }) = *rule {
+                accruals.push((name.clone(), account.clone(), accrual.clone()));
+            }
+        }
+
+        accruals
+    }
+
+    fn strategies(&self) -> Vec<Box<Strategy>> {
+        let mut strategies: Vec<Box<Strategy>> = Vec::new();
+
         for rule in self.rules.values() {
-            if let Rule::CompoundingInterest(ref c) = *rule {
-                iters.push(InterestStream::from(c.clone()));
+            match *rule {
+                Rule::DebtPayoff(ref d) => {
+                    strategies.push(Box::new(DebtPayoffStrategy::from(d.clone())))
+                }
+                Rule::Waterfall(ref w) => {
+                    strategies.push(Box::new(WaterfallStrategy::from(w.clone())))
+                }
+                Rule::Withdrawal(ref w) => {
+                    strategies.push(Box::new(WithdrawalStrategy::from(w.clone())))
+                }
+                _ => {}
             }
         }
 
-        SortedIterator::from_iter(iters.into_iter())
+        if let Some(ref tax) = self.tax {
+            strategies.push(Box::new(TaxStrategy::from(tax.clone())));
+        }
+
+        strategies
     }
 
-    pub fn history<D: Iterator<Item = NaiveDate>>(&self,
+    pub fn import_profile(&self, name: &str) -> Result<&ImportProfile> {
+        self.imports.get(name).ok_or_else(|| ErrorKind::UnknownProfile(String::from(name)).into())
+    }
+
+    pub fn scenario(&self, name: &str) -> Result<Plan> {
+        let scenario = match self.scenarios.get(name) {
+            Some(scenario) => scenario.clone(),
+            None => return Err(ErrorKind::UnknownScenario(String::from(name)).into()),
+        };
+
+        let mut plan = self.clone();
+        if let Some(accounts) = scenario.accounts {
+            plan.accounts.merge(accounts)?;
+        }
+        for rule in &scenario.remove {
+            if plan.rules.remove(rule).is_none() {
+                return Err(ErrorKind::UnknownRule(rule.clone()).into());
+            }
+        }
+        plan.rules.extend(scenario.rules);
+        Ok(plan)
+    }
+
+    pub fn with_parameter(&self, rule: &str, value: f64) -> Result<Plan> {
+        let mut plan = self.clone();
+        match plan.rules.get_mut(rule) {
+            Some(rule) => rule.set_parameter(value),
+            None => return Err(ErrorKind::UnknownRule(String::from(rule)).into()),
+        }
+        Ok(plan)
+    }
+
+    pub fn parameter(&self, rule: &str) -> Result<f64> {
+        match self.rules.get(rule) {
+            Some(r) => {
+                r.parameter().ok_or_else(|| ErrorKind::NoParameter(String::from(rule)).into())
+            }
+            None => Err(ErrorKind::UnknownRule(String::from(rule)).into()),
+        }
+    }
+
+    // checks everything that can be checked before running the plan
+    pub fn validate(&self) -> Result<()> {
+        self.accounts.validate()?;
+        self.with_events().map(|_| ())
+    }
+
+    pub fn start_date(&self) -> NaiveDate {
+        self.start_date.unwrap_or_else(today)
+    }
+
+    // the plan with all of its life events turned into accounts and rules
+    pub fn with_events(&self) -> Result<Plan> {
+        let mut plan = self.clone();
+        plan.events = HashMap::new();
+        // rules without a start date start with the plan, so that events change them on the
+        // same schedule the history runs them on
+        if let Some(start_date) = self.start_date {
+            for rule in plan.rules.values_mut() {
+                if rule.start_date().is_none() {
+                    rule.set_start_date(Some(start_date));
+                }
+            }
+        }
+        apply_events(&mut plan, &self.events)?;
+        Ok(plan)
+    }
+
+    // runs the plan up to a date, and evaluates the expression on it
+    pub fn evaluate_on(&self, expr: &Expr, date: NaiveDate) -> Result<Money> {
+        match self.moments(DateStream::once(Some(date)))?.pop() {
+            Some((_, accounts)) => accounts.evaluate(expr),
+            None => Ok(Money::zero()),
+        }
+    }
+
+    // the whole history, or whatever made it fail
+    pub fn moments<D: Iterator<Item = NaiveDate>>(&self,
                                                   dates: D)
-                                                  -> History<SortedIterator<Transaction,
-                                                                            RepeatingTransaction>,
-                                                             SortedIterator<CompoundedInterest,
-                                                                            InterestStream>,
-                                                             D> {
-        History::new((today(), self.accounts.clone()),
-                     self.transactions(),
-                     self.compounding(),
-                     dates)
+                                                  -> Result<Vec<(NaiveDate, Accounts)>> {
+        let mut history = self.history(dates)?;
+        let moments = history.by_ref().collect();
+        history.take_failure()?;
+        Ok(moments)
+    }
+
+    pub fn history<D: Iterator<Item = NaiveDate>>
+        (&self,
+         dates: D)
+         -> Result<History<SortedIterator<Transaction, RepeatingTransaction>,
+                           SortedIterator<CompoundedInterest, InterestStream>,
+                           D>> {
+        let plan = self.with_events()?;
+        Ok(History::new((plan.start_date(), plan.accounts.clone()),
+                            plan.transactions(),
+                            plan.compounding()?,
+                            plan.strategies(),
+                            dates)
+            .with_accruals(plan.accruals()))
+    }
+}
+
+// the number in a rule that the solver and friends can play with
+impl Rule {
+    pub fn parameter_name(&self) -> Option<&'static str> {
+        match *self {
+            Rule::RepeatingMoney(_) => Some("amount"),
+            Rule::CompoundingInterest(_) => Some("interest_rate"),
+            Rule::DebtPayoff(_) => Some("budget"),
+            Rule::Waterfall(ref w) => w.amount.as_ref().map(|_| "amount"),
+            Rule::Withdrawal(ref w) => {
+                match w.method {
+                    WithdrawalMethod::FixedReal { .. } => Some("amount"),
+                    WithdrawalMethod::Percent { .. } |
+                    WithdrawalMethod::Guardrails { .. } => Some("rate"),
+                    WithdrawalMethod::Minimum { .. } => None,
+                }
+            }
+            Rule::Expense(_) => Some("amount"),
+            Rule::Fee(_) => Some("fee"),
+        }
+    }
+
+    pub fn parameter(&self) -> Option<f64> {
+        match *self {
+            Rule::RepeatingMoney(ref t) => Some(t.amount.to_f64()),
+            Rule::CompoundingInterest(ref c) => Some(c.interest_rate),
+            Rule::DebtPayoff(ref d) => Some(d.budget.to_f64()),
+            Rule::Waterfall(ref w) => w.amount.as_ref().map(Money::to_f64),
+            Rule::Withdrawal(ref w) => {
+                match w.method {
+                    WithdrawalMethod::FixedReal { ref amount, .. } => Some(amount.to_f64()),
+                    WithdrawalMethod::Percent { rate } |
+                    WithdrawalMethod::Guardrails { rate, .. } => Some(rate),
+                    WithdrawalMethod::Minimum { .. } => None,
+                }
+            }
+            Rule::Expense(ref e) => Some(e.amount.to_f64()),
+            Rule::Fee(ref f) => Some(f.fee),
+        }
+    }
+
+    pub fn frequency(&self) -> Frequency {
+        match *self {
+            Rule::RepeatingMoney(ref t) => t.frequency.clone(),
+            Rule::CompoundingInterest(ref c) => c.period.clone(),
+            Rule::DebtPayoff(ref d) => d.frequency.clone(),
+            Rule::Waterfall(ref w) => w.frequency.clone(),
+            Rule::Withdrawal(ref w) => w.frequency.clone(),
+            Rule::Expense(ref e) => e.frequency.clone(),
+            Rule::Fee(ref f) => f.period.clone(),
+        }
+    }
+
+    pub fn start_date(&self) -> Option<NaiveDate> {
+        match *self {
+            Rule::RepeatingMoney(ref t) => t.start_date,
+            Rule::CompoundingInterest(ref c) => c.start_date,
+            Rule::DebtPayoff(ref d) => d.start_date,
+            Rule::Waterfall(ref w) => w.start_date,
+            Rule::Withdrawal(ref w) => w.start_date,
+            Rule::Expense(ref e) => e.start_date,
+            Rule::Fee(ref f) => f.start_date,
+        }
+    }
+
+    pub fn end_date(&self) -> Option<NaiveDate> {
+        match *self {
+            Rule::RepeatingMoney(ref t) => t.end_date,
+            Rule::CompoundingInterest(ref c) => c.end_date,
+            Rule::DebtPayoff(ref d) => d.end_date,
+            Rule::Waterfall(ref w) => w.end_date,
+            Rule::Withdrawal(ref w) => w.end_date,
+            Rule::Expense(ref e) => e.end_date,
+            Rule::Fee(ref f) => f.end_date,
+        }
+    }
+
+    pub fn set_start_date(&mut self, date: Option<NaiveDate>) {
+        match *self {
+            Rule::RepeatingMoney(ref mut t) => t.start_date = date,
+            Rule::CompoundingInterest(ref mut c) => c.start_date = date,
+            Rule::DebtPayoff(ref mut d) => d.start_date = date,
+            Rule::Waterfall(ref mut w) => w.start_date = date,
+            Rule::Withdrawal(ref mut w) => w.start_date = date,
+            Rule::Expense(ref mut e) => e.start_date = date,
+            Rule::Fee(ref mut f) => f.start_date = date,
+        }
+    }
+
+    pub fn set_end_date(&mut self, date: Option<NaiveDate>) {
+        match *self {
+            Rule::RepeatingMoney(ref mut t) => t.end_date = date,
+            Rule::CompoundingInterest(ref mut c) => c.end_date = date,
+            Rule::DebtPayoff(ref mut d) => d.end_date = date,
+            Rule::Waterfall(ref mut w) => w.end_date = date,
+            Rule::Withdrawal(ref mut w) => w.end_date = date,
+            Rule::Expense(ref mut e) => e.end_date = date,
+            Rule::Fee(ref mut f) => f.end_date = date,
+        }
+    }
+
+    pub fn set_parameter(&mut self, value: f64) {
+        match *self {
+            Rule::RepeatingMoney(ref mut t) => t.amount = Money::from(value),
+            Rule::CompoundingInterest(ref mut c) => c.interest_rate = value,
+            Rule::DebtPayoff(ref mut d) => d.budget = Money::from(value),
+            Rule::Waterfall(ref mut w) => {
+                if w.amount.is_some() {
+                    w.amount = Some(Money::from(value));
+                }
+            }
+            Rule::Withdrawal(ref mut w) => {
+                match w.method {
+                    WithdrawalMethod::FixedReal { ref mut amount, .. } => {
+                        *amount = Money::from(value)
+                    }
+                    WithdrawalMethod::Percent { ref mut rate } |
+                    WithdrawalMethod::Guardrails { ref mut rate, .. } => *rate = value,
+                    WithdrawalMethod::Minimum { .. } => {}
+                }
+            }
+            Rule::Expense(ref mut e) => e.amount = Money::from(value),
+            Rule::Fee(ref mut f) => f.fee = value,
+        }
     }
 }
 
@@ -97,29 +417,61 @@ pub struct RepeatingTransaction {
     amount: Amount,
     from: String,
     to: String,
+    tags: Vec<String>,
+    // the yearly rate the amount grows by, from a date
+    inflation: Option<(f64, NaiveDate)>,
 }
 
 impl RepeatingTransaction {
     fn new<T: Into<Amount>>(iterator: DateStream,
                             amount: T,
                             from: String,
-                            to: String)
+                            to: String,
+                            tags: Vec<String>)
                             -> RepeatingTransaction {
         RepeatingTransaction {
             iterator: iterator,
             amount: amount.into(),
             from: from,
             to: to,
+            tags: tags,
+            inflation: None,
         }
     }
+
+    fn with_inflation(mut self, rate: f64, since: NaiveDate) -> RepeatingTransaction {
+        self.inflation = Some((rate, since));
+        self
+    }
 }
 
 impl From<MoneyTransfer> for RepeatingTransaction {
     fn from(transfer: MoneyTransfer) -> RepeatingTransaction {
-        RepeatingTransaction::new(DateStream::from((transfer.frequency, transfer.start_date)),
+        RepeatingTransaction::new(DateStream::from((transfer.frequency, transfer.start_date))
+                                      .until(transfer.end_date),
                                   transfer.amount,
                                   transfer.from,
-                                  transfer.to)
+                                  transfer.to,
+                                  transfer.tags)
+    }
+}
+
+impl From<Expense> for RepeatingTransaction {
+    fn from(expense: Expense) -> RepeatingTransaction {
+        let to = expense.account();
+        let transaction =
+            RepeatingTransaction::new(DateStream::from((expense.frequency, expense.start_date))
+                                          .until(expense.end_date),
+                                      expense.amount,
+                                      expense.from,
+                                      to,
+                                      expense.tags);
+        match expense.inflation {
+            Some(rate) => {
+                transaction.with_inflation(rate, expense.start_date.unwrap_or_else(today))
+            }
+            None => transaction,
+        }
     }
 }
 
@@ -129,10 +481,18 @@ impl Iterator for RepeatingTransaction {
     fn next(&mut self) -> Option<Self::Item> {
         match self.iterator.next() {
             Some(next_date) => {
-                Some(Transaction::new(Amount::from(self.amount.clone()),
+                let amount = match (self.amount.clone(), self.inflation) {
+                    (Amount::Money(amount), Some((rate, since))) => {
+                        let years = years_between(since, next_date);
+                        Amount::Money(Money::from(amount.to_f64() * (1.0 + rate).powi(years)))
+                    }
+                    (amount, _) => amount,
+                };
+                Some(Transaction::new(amount,
                                       self.from.clone(),
                                       self.to.clone(),
-                                      next_date))
+                                      next_date)
+                    .with_tags(self.tags.clone()))
             }
             None => None,
         }
@@ -140,46 +500,73 @@ impl Iterator for RepeatingTransaction {
 }
 
 pub struct InterestStream {
+    rule: String,
     iterator: DateStream,
-    interest_rate: f64,
+    rates: RateSchedule,
+    period: Frequency,
     account: String,
+    accrual: Option<Accrual>,
+    paid_to: Option<String>,
 }
 
 impl InterestStream {
-    fn new(iterator: DateStream, interest_rate: f64, account: String) -> InterestStream {
-        InterestStream {
-            iterator: iterator,
-            interest_rate: interest_rate,
-            account: account,
-        }
+    fn new(name: &str,
+           rule: CompoundingInterest,
+           indices: &HashMap<String, Vec<IndexRate>>)
+           -> Result<InterestStream> {
+        Ok(InterestStream {
+            rule: String::from(name),
+            iterator: DateStream::from((rule.period.clone(), rule.start_date))
+                .until(rule.end_date),
+            rates: RateSchedule::new(rule.interest_rate, &rule.schedule, indices)?,
+            period: rule.period,
+            account: rule.account,
+            accrual: rule.accrual,
+            paid_to: None,
+        })
     }
-}
 
-fn interest_per_period(interest: f64, period: &Frequency) -> f64 {
-    match *period {
-        Frequency::Annually => interest,
-        Frequency::Monthly => interest / 12.0,
-        Frequency::BiWeekly => interest / 26.0,
-        Frequency::Once => interest,
+    fn paid_to(mut self, account: String) -> InterestStream {
+        self.paid_to = Some(account);
+        self
     }
 }
 
-impl From<CompoundingInterest> for InterestStream {
-    fn from(rule: CompoundingInterest) -> InterestStream {
-        let interest_rate = interest_per_period(rule.interest_rate, &rule.period);
-        InterestStream::new(DateStream::from((rule.period, rule.start_date)),
-                            interest_rate,
-                            rule.account)
+impl Frequency {
+    pub fn periods_per_year(&self) -> f64 {
+        match *self {
+            Frequency::Annually => 1.0,
+            Frequency::Monthly => 12.0,
+            Frequency::BiWeekly => 26.0,
+            Frequency::Once => 1.0,
+        }
     }
 }
 
+fn interest_per_period(interest: f64, period: &Frequency) -> f64 {
+    interest / period.periods_per_year()
+}
+
 impl Iterator for InterestStream {
     type Item = CompoundedInterest;
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.iterator.next() {
             Some(next_date) => {
-                Some(CompoundedInterest::new(next_date, self.interest_rate, self.account.clone()))
+                let rate = self.rates.rate_on(next_date);
+                let interest = CompoundedInterest::new(next_date,
+                                                       interest_per_period(rate, &self.period),
+                                                       self.account.clone());
+                let interest = match self.paid_to {
+                    Some(ref account) => interest.paid_to(account.clone()),
+                    None => interest,
+                };
+                Some(match self.accrual {
+                    Some(ref accrual) => {
+                        interest.with_accrual(self.rule.clone(), accrual.clone(), rate)
+                    }
+                    None => interest,
+                })
             }
             None => None,
         }
@@ -189,6 +576,7 @@ impl Iterator for InterestStream {
 // date streams are fun yay
 pub struct DateStream {
     date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
     func: fn(NaiveDate) -> Option<NaiveDate>,
 }
 
@@ -196,10 +584,16 @@ impl DateStream {
     fn new(date: Option<NaiveDate>, func: fn(NaiveDate) -> Option<NaiveDate>) -> DateStream {
         DateStream {
             date: if date.is_none() { Some(today()) } else { date },
+            end_date: None,
             func: func,
         }
     }
 
+    pub fn until(mut self, end_date: Option<NaiveDate>) -> DateStream {
+        self.end_date = end_date;
+        self
+    }
+
     pub fn yearly(date: Option<NaiveDate>) -> DateStream {
         DateStream::new(date, next_year)
     }
@@ -232,7 +626,10 @@ impl Iterator for DateStream {
     type Item = NaiveDate;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let previous_date = self.date;
+        let previous_date = match (self.date, self.end_date) {
+            (Some(date), Some(end_date)) if date > end_date => None,
+            (date, _) => date,
+        };
         self.date = previous_date.and_then(self.func);
         previous_date
     }
@@ -255,3 +652,59 @@ fn next_biweek(previous_date: NaiveDate) -> Option<NaiveDate> {
 fn once(_: NaiveDate) -> Option<NaiveDate> {
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan() -> Plan {
+        ::serde_yaml::from_str("accounts:\n  \
+                                  assets:\n    \
+                                    savings:\n      \
+                                      amount: 1000\n\
+                                rules:\n  \
+                                  rent:\n    \
+                                    amount: 1500\n    \
+                                    from: assets:savings\n    \
+                                    to: expenses:rent\n    \
+                                    frequency: Monthly\n\
+                                scenarios:\n  \
+                                  'Buy':\n    \
+                                    accounts:\n      \
+                                      assets:\n        \
+                                        savings:\n          \
+                                          amount: 500\n        \
+                                        house:\n          \
+                                          amount: 300000\n    \
+                                    remove: [rent]\n    \
+                                    rules:\n      \
+                                      mortgage:\n        \
+                                        amount: 1200\n        \
+                                        from: assets:savings\n        \
+                                        to: liabilities:mortgage\n        \
+                                        frequency: Monthly\n  \
+                                  'Typo':\n    \
+                                    remove: [rnet]")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_scenario_overlays_the_plan() {
+        let plan = plan();
+        let scenario = plan.scenario("Buy").unwrap();
+        let balances = scenario.accounts.eval().unwrap();
+        assert_eq!(balances["assets:savings"], Money::from(500));
+        assert_eq!(balances["assets:house"], Money::from(300000));
+        assert!(!scenario.rules.contains_key("rent"));
+        assert!(scenario.rules.contains_key("mortgage"));
+        // the plan itself is left alone
+        assert!(plan.rules.contains_key("rent"));
+    }
+
+    #[test]
+    fn test_scenario_errors() {
+        let plan = plan();
+        assert!(plan.scenario("Rent").is_err());
+        assert!(plan.scenario("Typo").is_err());
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+use events::next_on_or_after;
+use plan::*;
+
+// the balances the accounts really had on a day
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Actual {
+    pub date: NaiveDate,
+    pub accounts: Accounts,
+}
+
+#[derive(Clone, Debug)]
+pub struct Variance {
+    pub date: NaiveDate,
+    pub account: String,
+    pub projected: Money,
+    pub actual: Money,
+}
+
+impl Variance {
+    pub fn difference(&self) -> Money {
+        self.actual.clone() - self.projected.clone()
+    }
+
+    // how far off the plan the account is, as a percentage of where it should be. more money
+    // in a liability is behind the plan.
+    pub fn percent(&self) -> Option<f64> {
+        let projected = self.projected.to_f64();
+        if projected == 0.0 {
+            return None;
+        }
+        let percent = self.difference().to_f64() / projected.abs() * 100.0;
+        Some(if is_liability(&self.account) {
+            -percent
+        } else {
+            percent
+        })
+    }
+}
+
+fn is_liability(account: &str) -> bool {
+    account.starts_with("liabilities")
+}
+
+// the assets less the liabilities of the balances, for the accounts they have
+pub fn net_worth(balances: &HashMap<String, Money>) -> Money {
+    balances.iter()
+        .filter(|&(path, _)| path.starts_with("assets") || is_liability(path))
+        .map(|(path, balance)| {
+            if is_liability(path) {
+                -balance.clone()
+            } else {
+                balance.clone()
+            }
+        })
+        .sum()
+}
+
+// compares every actual balance with what the plan had for the account on that day, and the net
+// worth of those accounts too. actuals from before the plan starts are left out.
+pub fn variances(plan: &Plan, actuals: &[Actual]) -> Result<Vec<Variance>> {
+    let mut actuals: Vec<&Actual> =
+        actuals.iter().filter(|actual| actual.date >= plan.start_date()).collect();
+    actuals.sort_by_key(|actual| actual.date);
+    let dates: Vec<NaiveDate> = actuals.iter().map(|actual| actual.date).collect();
+    let moments = plan.moments(dates.into_iter())?;
+
+    let mut variances = Vec::new();
+    for (actual, &(date, ref projected)) in actuals.into_iter().zip(moments.iter()) {
+        let balances = actual.accounts.eval()?;
+        let mut paths: Vec<&String> = balances.keys().collect();
+        paths.sort();
+
+        let mut projected_balances = HashMap::new();
+        for path in paths {
+            let balance = projected.balance(path).unwrap_or_else(|_| Money::zero());
+            projected_balances.insert(path.clone(), balance.clone());
+            variances.push(Variance {
+                date: date,
+                account: path.clone(),
+                projected: balance,
+                actual: balances[path].clone(),
+            });
+        }
+        variances.push(Variance {
+            date: date,
+            account: String::from("net worth"),
+            projected: net_worth(&projected_balances),
+            actual: net_worth(&balances),
+        });
+    }
+    Ok(variances)
+}
+
+// the plan starting over from the balances at the end of the day of the actual. rules keep their
+// schedules, so they carry on from the first day they would have happened on after it, and rules
+// that are over are left out.
+pub fn reforecast(plan: &Plan, actual: &Actual) -> Result<Plan> {
+    let start_date = plan.start_date();
+    let mut plan = plan.with_events()?;
+    plan.start_date = Some(actual.date);
+
+    let mut rules = HashMap::new();
+    for (name, mut rule) in plan.rules.drain() {
+        if rule.start_date().is_none() {
+            rule.set_start_date(Some(start_date));
+        }
+        if let Some(date) = next_on_or_after(&rule, actual.date.succ()) {
+            rule.set_start_date(Some(date));
+            rules.insert(name, rule);
+        }
+    }
+    plan.rules = rules;
+
+    // only the balances change, so limits and book values stay as they were. derived accounts
+    // still come from their expressions.
+    for (path, amount) in actual.accounts.eval()? {
+        let mut account = match plan.accounts.get(&path) {
+            Ok(&Accounts::Leaf(Account::Simple(ref s))) => s.clone(),
+            Ok(&Accounts::Leaf(Account::Derived(_))) => continue,
+            // the plan has sub accounts where the actual only has the one balance
+            Ok(_) => bail!(ErrorKind::AlreadyExists(path)),
+            Err(_) => SimpleAccount::new(Money::zero()),
+        };
+        account.amount = amount;
+        plan.accounts.set_account(path, Account::Simple(account))?;
+    }
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variance_against_plan() {
+        let plan: Plan = ::serde_yaml::from_str("start_date: 2017-01-01\n\
+                                                 accounts:\n  \
+                                                   assets:\n    \
+                                                     savings:\n      \
+                                                       amount: 1000\n\
+                                                 rules:\n  \
+                                                   saving:\n    \
+                                                     amount: 100\n    \
+                                                     from: income\n    \
+                                                     to: assets:savings\n    \
+                                                     frequency: Annually\n    \
+                                                     start_date: 2017-01-01")
+            .unwrap();
+        let mut accounts = Accounts::root();
+        accounts.set_account(String::from("assets:savings"),
+                             Account::Simple(SimpleAccount::new(Money::from(1050))))
+            .unwrap();
+        let actual = Actual {
+            date: NaiveDate::from_ymd(2017, 6, 1),
+            accounts: accounts,
+        };
+
+        let variances = variances(&plan, &[actual.clone()]).unwrap();
+        assert_eq!(variances[0].projected, Money::from(1100));
+        assert_eq!(variances[0].difference(), Money::from(-50));
+
+        // the next saving is a year after the last one
+        let reforecast = reforecast(&plan, &actual).unwrap();
+        assert_eq!(reforecast.rules["saving"].start_date(),
+                   Some(NaiveDate::from_ymd(2018, 1, 1)));
+    }
+
+    #[test]
+    fn test_net_worth_and_liabilities() {
+        let plan: Plan = ::serde_yaml::from_str("start_date: 2017-01-01\n\
+                                                 accounts:\n  \
+                                                   assets:\n    \
+                                                     savings:\n      \
+                                                       amount: 1000\n  \
+                                                   liabilities:\n    \
+                                                     visa:\n      \
+                                                       amount: 200\n\
+                                                 rules: {}")
+            .unwrap();
+        let mut accounts = Accounts::root();
+        accounts.set_account(String::from("assets:savings"),
+                             Account::Simple(SimpleAccount::new(Money::from(1000))))
+            .unwrap();
+        accounts.set_account(String::from("liabilities:visa"),
+                             Account::Simple(SimpleAccount::new(Money::from(300))))
+            .unwrap();
+        let actual = Actual {
+            date: NaiveDate::from_ymd(2017, 6, 1),
+            accounts: accounts,
+        };
+
+        let variances = variances(&plan, &[actual]).unwrap();
+        assert_eq!(variances.len(), 3);
+        // owing more than the plan is behind it
+        assert_eq!(variances[1].account, "liabilities:visa");
+        assert_eq!(variances[1].difference(), Money::from(100));
+        assert_eq!(variances[1].percent(), Some(-50.0));
+
+        assert_eq!(variances[2].account, "net worth");
+        assert_eq!(variances[2].projected, Money::from(800));
+        assert_eq!(variances[2].actual, Money::from(700));
+        assert_eq!(variances[2].percent(), Some(-12.5));
+    }
+
+    #[test]
+    fn test_reforecast_keeps_the_accounts() {
+        let plan: Plan = ::serde_yaml::from_str("start_date: 2017-01-01\n\
+                                                 accounts:\n  \
+                                                   assets:\n    \
+                                                     brokerage:\n      \
+                                                       amount: 1200\n      \
+                                                       book_value: 1000\n  \
+                                                   net:\n    \
+                                                     expression: assets\n\
+                                                 rules: {}")
+            .unwrap();
+        let mut actual = Actual {
+            date: NaiveDate::from_ymd(2017, 6, 1),
+            accounts: plan.accounts.clone(),
+        };
+        actual.accounts
+            .set_account(String::from("assets:brokerage"),
+                         Account::Simple(SimpleAccount::new(Money::from(1500))))
+            .unwrap();
+
+        let reforecast = reforecast(&plan, &actual).unwrap();
+        match reforecast.accounts.get("assets:brokerage") {
+            Ok(&Accounts::Leaf(Account::Simple(ref s))) => {
+                assert_eq!(s.amount, Money::from(1500));
+                assert_eq!(s.book_value, Some(Money::from(1000)));
+            }
+            _ => panic!("the brokerage account should be simple"),
+        }
+        match reforecast.accounts.get("net") {
+            Ok(&Accounts::Leaf(Account::Derived(_))) => {}
+            _ => panic!("the net account should still be derived"),
+        }
+        assert_eq!(reforecast.accounts.balance("net").unwrap(), Money::from(1500));
+    }
+}
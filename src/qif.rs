@@ -0,0 +1,138 @@
+use chrono::prelude::*;
+
+use errors::*;
+use imports::*;
+
+// lists of categories, classes and memorized transactions are in the same file as the
+// transactions, and so is the list of accounts
+const SKIPPED: [&'static str; 5] = ["!Account", "!Option", "!Type:Cat", "!Type:Class",
+                                    "!Type:Memorized"];
+
+#[derive(Default)]
+struct Record {
+    date: Option<String>,
+    amount: Option<String>,
+    payee: Option<String>,
+    memo: Option<String>,
+    line: usize,
+}
+
+// dates are usually month first, like 1/31/2017, 1/31/17 or 1/31'17 (the ' is for years after
+// 2000). the profile's date format is used instead when it has one.
+fn parse_date(text: &str, format: &Option<String>) -> Option<NaiveDate> {
+    let text = text.trim();
+    if let Some(ref format) = *format {
+        return NaiveDate::parse_from_str(text, format).ok();
+    }
+
+    let parts: Vec<u32> = match text.split(|c| c == '/' || c == '\'' || c == '-')
+        .map(|part| part.trim().parse::<u32>())
+        .collect() {
+        Ok(parts) => parts,
+        Err(_) => return None,
+    };
+    if parts.len() != 3 {
+        return None;
+    }
+    let year = match parts[2] as i32 {
+        year @ 0..=69 => year + 2000,
+        year @ 70..=99 => year + 1900,
+        year => year,
+    };
+    NaiveDate::from_ymd_opt(year, parts[0], parts[1])
+}
+
+impl Record {
+    fn to_row(&self,
+              profile: &ImportProfile,
+              mappings: &[(::regex::Regex, String)])
+              -> Result<ImportedRow> {
+        let format = &profile.date_format;
+        let date = match self.date.as_ref().and_then(|date| parse_date(date, format)) {
+            Some(date) => date,
+            None => bail!(ErrorKind::InvalidRow(self.line, String::from("date"))),
+        };
+        let amount = match self.amount.as_ref().and_then(|amount| parse_amount(amount)) {
+            Some(amount) => amount,
+            None => bail!(ErrorKind::InvalidRow(self.line, String::from("amount"))),
+        };
+        let description = self.payee.clone().or_else(|| self.memo.clone()).unwrap_or_default();
+        Ok(profile.row(date, description, amount, mappings))
+    }
+}
+
+// reads the transactions out of a qif file. every line starts with a letter saying what is on
+// it, and a ^ ends each transaction. there is no balance in a qif file.
+pub fn read(contents: &str, profile: &ImportProfile) -> Result<Statement> {
+    let mappings = profile.compiled_mappings()?;
+    let mut rows = Vec::new();
+    let mut record = Record::default();
+    let mut skipping = false;
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('!') {
+            skipping = SKIPPED.iter().any(|header| line.starts_with(header));
+            continue;
+        }
+        if skipping {
+            continue;
+        }
+        if record.line == 0 {
+            record.line = index + 1;
+        }
+
+        let code = line.chars().next().unwrap();
+        let text = String::from(line[code.len_utf8()..].trim());
+        match code {
+            'D' => record.date = Some(text),
+            // U is the same amount, written by newer versions of quicken
+            'T' | 'U' => {
+                if record.amount.is_none() {
+                    record.amount = Some(text);
+                }
+            }
+            'P' => record.payee = Some(text),
+            'M' => record.memo = Some(text),
+            '^' => {
+                if record.date.is_some() || record.amount.is_some() {
+                    rows.push(record.to_row(profile, &mappings)?);
+                }
+                record = Record::default();
+            }
+            // splits, categories, cleared status, cheque numbers and addresses
+            _ => {}
+        }
+    }
+
+    Ok(Statement {
+        rows: rows,
+        balances: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use money::Money;
+    use accounts::Amount;
+
+    #[test]
+    fn test_read_qif() {
+        let qif = "!Type:Bank\nD1/31'17\nT-1,200.00\nPLandlord\nLRent\n^\n\
+                   D2/ 1/2017\nU2,500.00\nT2,500.00\nMPayroll deposit\n^\n";
+        let profile: ImportProfile = ::serde_yaml::from_str("account: assets:chequing").unwrap();
+        let statement = read(qif, &profile).unwrap();
+
+        assert_eq!(statement.rows.len(), 2);
+        let rent = &statement.rows[0].transaction;
+        assert_eq!(rent.date, NaiveDate::from_ymd(2017, 1, 31));
+        assert_eq!(rent.to, UNCATEGORIZED_EXPENSES);
+        assert_eq!(rent.amount, Amount::Money(Money::from(1200)));
+        assert_eq!(statement.rows[1].description, "Payroll deposit");
+        assert_eq!(statement.rows[1].transaction.date, NaiveDate::from_ymd(2017, 2, 1));
+    }
+}
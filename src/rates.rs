@@ -0,0 +1,230 @@
+use std::fmt;
+use std::collections::HashMap;
+use std::str::FromStr;
+use chrono::prelude::*;
+
+use serde::ser::{Serialize, Serializer};
+use serde::de::{Deserialize, Deserializer, Visitor, Error};
+
+use errors::*;
+
+// an interest rate is either a number, or a spread over a named index like 'prime + 1.5%'
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rate {
+    Fixed(f64),
+    Indexed { index: String, spread: f64 },
+}
+
+// a rate that takes effect on a date, and stays until the next one
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduledRate {
+    pub from: NaiveDate,
+    pub rate: Rate,
+}
+
+// the value of a named index (like prime) from a date on
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexRate {
+    pub from: NaiveDate,
+    pub rate: f64,
+}
+
+fn percent(s: &str) -> ::std::result::Result<f64, String> {
+    let s = s.trim();
+    if s.ends_with('%') {
+        s[..s.len() - 1].trim().parse::<f64>().map(|p| p / 100.0).map_err(|e| e.to_string())
+    } else {
+        s.parse::<f64>().map_err(|e| e.to_string())
+    }
+}
+
+impl FromStr for Rate {
+    type Err = String;
+
+    fn from_str(s: &str) -> ::std::result::Result<Rate, String> {
+        if let Ok(rate) = percent(s) {
+            return Ok(Rate::Fixed(rate));
+        }
+        // index names can have dashes in them, so only split off a spread that parses
+        let (index, spread) = match s.rfind(|c| c == '+' || c == '-') {
+            Some(i) => {
+                match percent(&s[i + 1..]) {
+                    Ok(spread) if &s[i..i + 1] == "-" => (&s[..i], -spread),
+                    Ok(spread) => (&s[..i], spread),
+                    Err(_) => (s, 0.0),
+                }
+            }
+            None => (s, 0.0),
+        };
+        let index = index.trim();
+        if index.is_empty() {
+            return Err(format!("expected a rate or an index, got '{}'", s));
+        }
+        Ok(Rate::Indexed {
+            index: String::from(index),
+            spread: spread,
+        })
+    }
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Rate::Fixed(rate) => write!(f, "{}", rate),
+            Rate::Indexed { ref index, spread } if spread < 0.0 => {
+                write!(f, "{} - {}%", index, -spread * 100.0)
+            }
+            Rate::Indexed { ref index, spread } => write!(f, "{} + {}%", index, spread * 100.0),
+        }
+    }
+}
+
+impl Serialize for Rate {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        match *self {
+            Rate::Fixed(rate) => serializer.serialize_f64(rate),
+            _ => serializer.serialize_str(&self.to_string()),
+        }
+    }
+}
+
+impl Deserialize for Rate {
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+        where D: Deserializer
+    {
+        deserializer.deserialize(RateVisitor)
+    }
+}
+
+struct RateVisitor;
+
+impl Visitor for RateVisitor {
+    type Value = Rate;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an interest rate, or an index plus or minus a percentage")
+    }
+
+    fn visit_f64<E>(self, v: f64) -> ::std::result::Result<Self::Value, E>
+        where E: Error
+    {
+        Ok(Rate::Fixed(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> ::std::result::Result<Self::Value, E>
+        where E: Error
+    {
+        Ok(Rate::Fixed(v as f64))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> ::std::result::Result<Self::Value, E>
+        where E: Error
+    {
+        Ok(Rate::Fixed(v as f64))
+    }
+
+    fn visit_str<E>(self, v: &str) -> ::std::result::Result<Self::Value, E>
+        where E: Error
+    {
+        v.parse().map_err(E::custom)
+    }
+}
+
+fn in_effect<T: Clone>(changes: &[(NaiveDate, T)], date: NaiveDate) -> Option<T> {
+    changes.iter().rev().find(|&&(from, _)| from <= date).map(|&(_, ref value)| value.clone())
+}
+
+// every rate an interest rule will ever have, worked out ahead of time
+#[derive(Clone, Debug)]
+pub struct RateSchedule {
+    base: f64,
+    changes: Vec<(NaiveDate, f64)>,
+}
+
+impl RateSchedule {
+    pub fn new(base: f64,
+               schedule: &[ScheduledRate],
+               indices: &HashMap<String, Vec<IndexRate>>)
+               -> Result<RateSchedule> {
+        let mut schedule: Vec<(NaiveDate, Rate)> =
+            schedule.iter().map(|s| (s.from, s.rate.clone())).collect();
+        schedule.sort_by_key(|&(from, _)| from);
+
+        // the rate changes whenever the schedule does, or an index it is using does
+        let mut dates: Vec<NaiveDate> = schedule.iter().map(|&(from, _)| from).collect();
+        let mut resolved = HashMap::new();
+        for &(_, ref rate) in &schedule {
+            if let Rate::Indexed { ref index, .. } = *rate {
+                let mut values: Vec<(NaiveDate, f64)> = match indices.get(index) {
+                    Some(values) => values.iter().map(|v| (v.from, v.rate)).collect(),
+                    None => return Err(ErrorKind::UnknownIndex(index.clone()).into()),
+                };
+                values.sort_by_key(|&(from, _)| from);
+                dates.extend(values.iter().map(|&(from, _)| from));
+                resolved.insert(index.clone(), values);
+            }
+        }
+        dates.sort();
+        dates.dedup();
+
+        let mut changes = Vec::new();
+        for date in dates {
+            let rate = match in_effect(&schedule, date) {
+                Some(Rate::Fixed(rate)) => rate,
+                // before an index has a value, it starts at its first one
+                Some(Rate::Indexed { index, spread }) => {
+                    let values = &resolved[&index];
+                    in_effect(values, date).or_else(|| values.first().map(|&(_, v)| v))
+                        .unwrap_or(0.0) + spread
+                }
+                None => continue,
+            };
+            changes.push((date, rate));
+        }
+
+        Ok(RateSchedule {
+            base: base,
+            changes: changes,
+        })
+    }
+
+    pub fn rate_on(&self, date: NaiveDate) -> f64 {
+        in_effect(&self.changes, date).unwrap_or(self.base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indexed_schedule() {
+        assert_eq!("prime + 1.5%".parse::<Rate>(),
+                   Ok(Rate::Indexed {
+                       index: String::from("prime"),
+                       spread: 0.015,
+                   }));
+
+        let mut indices = HashMap::new();
+        indices.insert(String::from("prime"),
+                       vec![IndexRate {
+                                from: NaiveDate::from_ymd(2017, 1, 1),
+                                rate: 0.03,
+                            },
+                            IndexRate {
+                                from: NaiveDate::from_ymd(2018, 1, 1),
+                                rate: 0.04,
+                            }]);
+        let schedule = vec![ScheduledRate {
+                                from: NaiveDate::from_ymd(2017, 6, 1),
+                                rate: "prime + 1%".parse().unwrap(),
+                            }];
+        let rates = RateSchedule::new(0.02, &schedule, &indices).unwrap();
+
+        assert_eq!(rates.rate_on(NaiveDate::from_ymd(2017, 3, 1)), 0.02);
+        assert!((rates.rate_on(NaiveDate::from_ymd(2017, 7, 1)) - 0.04).abs() < 1e-9);
+        assert!((rates.rate_on(NaiveDate::from_ymd(2018, 7, 1)) - 0.05).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::{BTreeMap, HashMap};
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::*;
+use imports::ImportedRow;
+use plan::*;
+
+// amounts within this fraction of each other are the same payment (a bill that changes a bit)
+const AMOUNT_TOLERANCE: f64 = 0.1;
+// this fraction of the gaps between payments have to fit the frequency
+const REGULARITY: f64 = 0.75;
+
+// descriptions usually have reference numbers and dates in them, which change every time
+fn payee(description: &str) -> String {
+    description.to_lowercase()
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    (28..32).rev().find(|day| NaiveDate::from_ymd_opt(year, month, *day).is_some()).unwrap()
+}
+
+fn add_months(date: NaiveDate, months: u32) -> NaiveDate {
+    let total = date.year() * 12 + date.month0() as i32 + months as i32;
+    let (year, month) = (total / 12, total as u32 % 12 + 1);
+    NaiveDate::from_ymd(year, month, date.day().min(days_in_month(year, month)))
+}
+
+pub fn next_date(date: NaiveDate, frequency: &Frequency) -> Option<NaiveDate> {
+    match *frequency {
+        Frequency::Annually => Some(add_months(date, 12)),
+        Frequency::Monthly => Some(add_months(date, 1)),
+        Frequency::BiWeekly => Some(date + ::chrono::Duration::weeks(2)),
+        Frequency::Once => None,
+    }
+}
+
+// the frequency that fits the gaps (in days) between payments, and how many payments it takes to
+// tell
+fn frequency(gaps: &[i64]) -> Option<(Frequency, usize)> {
+    let mut sorted = gaps.to_vec();
+    sorted.sort();
+    let median = match sorted.get(sorted.len() / 2) {
+        Some(median) => *median,
+        None => return None,
+    };
+
+    let (frequency, low, high, payments) = match median {
+        12..=16 => (Frequency::BiWeekly, 12, 16, 3),
+        26..=35 => (Frequency::Monthly, 26, 35, 3),
+        350..=380 => (Frequency::Annually, 350, 380, 2),
+        _ => return None,
+    };
+    let fitting = gaps.iter().filter(|gap| low <= **gap && **gap <= high).count();
+    if (fitting as f64) < REGULARITY * gaps.len() as f64 {
+        return None;
+    }
+    Some((frequency, payments))
+}
+
+// groups payments to the same payee with about the same amount
+fn clusters(rows: &[ImportedRow]) -> Vec<Vec<(&ImportedRow, f64)>> {
+    let mut groups: HashMap<(String, String, String), Vec<(&ImportedRow, f64)>> = HashMap::new();
+    for row in rows {
+        if let Amount::Money(ref amount) = row.transaction.amount {
+            let key = (payee(&row.description),
+                       row.transaction.from.clone(),
+                       row.transaction.to.clone());
+            groups.entry(key).or_insert_with(Vec::new).push((row, amount.to_f64()));
+        }
+    }
+
+    let mut clusters = Vec::new();
+    for (_, mut group) in groups {
+        group.sort_by(|left, right| left.1.partial_cmp(&right.1).unwrap());
+        let mut cluster: Vec<(&ImportedRow, f64)> = Vec::new();
+        for (row, amount) in group {
+            let fits = cluster.first()
+                .map_or(true, |&(_, first)| amount <= first * (1.0 + AMOUNT_TOLERANCE) + 0.01);
+            if !fits {
+                clusters.push(cluster);
+                cluster = Vec::new();
+            }
+            cluster.push((row, amount));
+        }
+        clusters.push(cluster);
+    }
+    clusters
+}
+
+// a rule for every payment that keeps coming back, starting with the first payment expected on
+// or after the from date. the amount is the average of the payments.
+pub fn suggest_rules(rows: &[ImportedRow], from: NaiveDate) -> BTreeMap<String, Rule> {
+    let mut rules = BTreeMap::new();
+    for mut cluster in clusters(rows) {
+        cluster.sort_by_key(|&(row, _)| row.transaction.date);
+        let gaps: Vec<i64> = cluster.windows(2)
+            .map(|pair| (pair[1].0.transaction.date - pair[0].0.transaction.date).num_days())
+            .collect();
+        let frequency = match frequency(&gaps) {
+            Some((frequency, payments)) if cluster.len() >= payments => frequency,
+            _ => continue,
+        };
+
+        let total: f64 = cluster.iter().map(|&(_, amount)| amount).sum();
+        let average = total / cluster.len() as f64;
+        let &(last, _) = cluster.last().unwrap();
+        let mut start_date = last.transaction.date;
+        while start_date <= last.transaction.date || start_date < from {
+            start_date = next_date(start_date, &frequency).unwrap();
+        }
+
+        // the same payee can have a couple of different payments
+        let base = payee(&last.description);
+        let mut name = base.clone();
+        let mut count = 1;
+        while rules.contains_key(&name) {
+            count += 1;
+            name = format!("{} ({})", base, count);
+        }
+        rules.insert(name,
+                     Rule::RepeatingMoney(MoneyTransfer {
+                         amount: Money::from((average * 100.0).round() / 100.0),
+                         from: last.transaction.from.clone(),
+                         to: last.transaction.to.clone(),
+                         frequency: frequency,
+                         start_date: Some(start_date),
+                         end_date: None,
+                         tags: Vec::new(),
+                     }));
+    }
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use imports::ImportProfile;
+
+    #[test]
+    fn test_suggest_monthly_bill() {
+        let profile: ImportProfile = ::serde_yaml::from_str("account: assets:chequing").unwrap();
+        let mut rows = Vec::new();
+        for &(month, amount) in &[(1, -61.20), (2, -59.80), (3, -60.00), (4, -61.00)] {
+            rows.push(profile.row(NaiveDate::from_ymd(2017, month, 3),
+                                  format!("HYDRO #{}", 1000 + month),
+                                  amount,
+                                  &[]));
+        }
+        rows.push(profile.row(NaiveDate::from_ymd(2017, 2, 14),
+                              String::from("FLOWERS"),
+                              -40.0,
+                              &[]));
+
+        let rules = suggest_rules(&rows, NaiveDate::from_ymd(2017, 5, 1));
+        assert_eq!(rules.len(), 1);
+        match rules["hydro"] {
+            Rule::RepeatingMoney(ref rule) => {
+                assert_eq!(rule.amount, Money::from(60.5));
+                assert_eq!(rule.start_date, Some(NaiveDate::from_ymd(2017, 5, 3)));
+            }
+            _ => panic!("expected a money transfer"),
+        }
+    }
+}
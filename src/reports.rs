@@ -0,0 +1,189 @@
+use chrono;
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+use plan::*;
+use goals;
+use imports::Statement;
+use output::{Field, Report};
+
+// a column for every account, which is easier to read
+pub fn forecast_columns(account_names: &[String],
+                        moments: &[(NaiveDate, Accounts)])
+                        -> Result<Report> {
+    let mut header = vec![String::from("Date")];
+    header.extend(account_names.iter().cloned());
+    let mut report = Report::new(header);
+
+    for &(date, ref moment) in moments {
+        let evaluated = moment.eval()?;
+        let mut result = vec![Field::from(date)];
+        for name in account_names {
+            result.push(Field::from(evaluated.get(name).cloned()));
+        }
+        report.add_row(result);
+    }
+    Ok(report)
+}
+
+// a row for every account on every date, for other programs. with a journal, the money that went
+// into and came out of the account since the date before is on the row too.
+pub fn forecast_rows(account_names: &[String],
+                     moments: &[(NaiveDate, Accounts)],
+                     journal: Option<&[(Transaction, Money)]>)
+                     -> Result<Report> {
+    let mut header = vec!["Date", "Account", "Balance"];
+    if journal.is_some() {
+        header.push("In");
+        header.push("Out");
+    }
+    let mut report = Report::new(header);
+
+    let mut previous = None;
+    for &(date, ref moment) in moments {
+        let evaluated = moment.eval()?;
+        // the money that moved since the date before
+        let period: Vec<_> = journal.unwrap_or(&[])
+            .iter()
+            .filter(|entry| entry.0.date <= date && Some(entry.0.date) > previous)
+            .collect();
+
+        for name in account_names {
+            let mut result = vec![Field::from(date),
+                                  Field::from(name.as_str()),
+                                  Field::from(evaluated.get(name).cloned())];
+            if journal.is_some() {
+                let incoming: Money = period.iter()
+                    .filter(|entry| entry.0.to == *name)
+                    .map(|entry| entry.1.clone())
+                    .sum();
+                let outgoing: Money = period.iter()
+                    .filter(|entry| entry.0.from == *name)
+                    .map(|entry| entry.1.clone())
+                    .sum();
+                result.push(Field::from(incoming));
+                result.push(Field::from(outgoing));
+            }
+            report.add_row(result);
+        }
+        previous = Some(date);
+    }
+    Ok(report)
+}
+
+// every goal checked each month for <years>. other programs get nothing for when a goal that
+// isn't achieved is, rather than 'never'.
+pub fn goals(plan: &Plan, years: usize, readable: bool) -> Result<Report> {
+    let start = plan.start_date();
+    let horizon = start + chrono::Duration::days(365 * years as i64);
+    let moments = plan.moments(DateStream::monthly(Some(start))
+        .take_while(|date| *date <= horizon))?;
+
+    let mut report = Report::new(vec!["Goal",
+                                      "Condition",
+                                      "By",
+                                      "Achieved",
+                                      "Shortfall",
+                                      "Extra Monthly"]);
+
+    let mut names: Vec<_> = plan.goals.keys().collect();
+    names.sort();
+    for name in names {
+        let status = goals::evaluate(name, &plan.goals[name], start, &moments)?;
+        let achieved = match status.achieved {
+            Some(date) => Field::from(date),
+            None if readable => Field::from("never"),
+            None => Field::Empty,
+        };
+        report.add_row(vec![Field::from(status.name.as_str()),
+                            Field::from(status.goal.condition.to_string()),
+                            Field::from(status.goal.by),
+                            achieved,
+                            Field::from(status.shortfall.clone()),
+                            Field::from(status.extra_monthly.clone())]);
+    }
+    Ok(report)
+}
+
+// the transactions read out of an export
+pub fn statement(statement: &Statement) -> Report {
+    let mut report = Report::new(vec!["Date", "Description", "From", "To", "Amount"]);
+    for row in &statement.rows {
+        let transaction = &row.transaction;
+        let amount = match transaction.amount {
+            Amount::Money(ref amount) => Field::from(amount.clone()),
+            ref amount => Field::from(amount.to_string()),
+        };
+        report.add_row(vec![Field::from(transaction.date),
+                            Field::from(row.description.as_str()),
+                            Field::from(transaction.from.as_str()),
+                            Field::from(transaction.to.as_str()),
+                            amount]);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use output::Format;
+
+    #[test]
+    fn test_flows() {
+        let plan: Plan = ::serde_yaml::from_str("start_date: 2017-01-01\n\
+                                                 accounts:\n  \
+                                                   assets:\n    \
+                                                     chequing:\n      \
+                                                       amount: 1000\n    \
+                                                     savings:\n      \
+                                                       amount: 0\n\
+                                                 rules:\n  \
+                                                   saving:\n    \
+                                                     amount: 100\n    \
+                                                     from: assets:chequing\n    \
+                                                     to: assets:savings\n    \
+                                                     frequency: Annually\n    \
+                                                     start_date: 2017-03-01")
+            .unwrap();
+        let mut names = plan.accounts.get_account_names();
+        names.sort();
+        let mut history = plan.history(DateStream::yearly(Some(plan.start_date())).take(2))
+            .unwrap()
+            .with_journal();
+        let moments: Vec<_> = history.by_ref().collect();
+        history.take_failure().unwrap();
+
+        let mut out = Vec::new();
+        forecast_rows(&names, &moments, Some(history.journal()))
+            .unwrap()
+            .write(Format::Csv, &mut out)
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "date,account,balance,in,out\n\
+                    2017-01-01,assets:chequing,1000.00,0.00,0.00\n\
+                    2017-01-01,assets:savings,0.00,0.00,0.00\n\
+                    2018-01-01,assets:chequing,900.00,0.00,100.00\n\
+                    2018-01-01,assets:savings,100.00,100.00,0.00\n");
+    }
+
+    #[test]
+    fn test_statement() {
+        let profile: ::imports::ImportProfile =
+            ::serde_yaml::from_str("account: assets:chequing").unwrap();
+        let statement = Statement {
+            rows: vec![profile.row(NaiveDate::from_ymd(2017, 1, 5),
+                                   String::from("Grocer, Inc"),
+                                   -42.5,
+                                   &[])],
+            balances: Vec::new(),
+        };
+
+        let mut out = Vec::new();
+        super::statement(&statement).write(Format::Csv, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "date,description,from,to,amount\n\
+                    2017-01-05,\"Grocer, Inc\",assets:chequing,expenses:uncategorized,42.50\n");
+    }
+}
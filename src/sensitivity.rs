@@ -0,0 +1,102 @@
+use std::str::FromStr;
+use chrono::prelude::*;
+
+use money::Money;
+use errors::*;
+use expression::Expr;
+use plan::*;
+
+// a range of changes to the parameter of a rule, written as 'RULE:LOW:HIGH:STEPS'.
+// the low and high are added to whatever the parameter is in the plan.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variation {
+    pub rule: String,
+    pub low: f64,
+    pub high: f64,
+    pub steps: usize,
+}
+
+impl FromStr for Variation {
+    type Err = String;
+
+    fn from_str(s: &str) -> ::std::result::Result<Variation, String> {
+        // rule names can have colons in them, so split from the right
+        let parts: Vec<&str> = s.rsplitn(4, ':').collect();
+        if parts.len() != 4 {
+            return Err(format!("expected RULE:LOW:HIGH:STEPS, got '{}'", s));
+        }
+        let number = |part: &str| part.parse::<f64>().map_err(|e| e.to_string());
+        Ok(Variation {
+            rule: String::from(parts[3]),
+            low: number(parts[2])?,
+            high: number(parts[1])?,
+            steps: parts[0].parse().map_err(|e: ::std::num::ParseIntError| e.to_string())?,
+        })
+    }
+}
+
+impl Variation {
+    pub fn deltas(&self) -> Vec<f64> {
+        if self.steps < 2 {
+            return vec![self.low];
+        }
+        let step = (self.high - self.low) / (self.steps - 1) as f64;
+        (0..self.steps).map(|i| self.low + step * i as f64).collect()
+    }
+}
+
+pub struct Grid {
+    pub rows: Vec<f64>,
+    pub columns: Vec<f64>,
+    pub values: Vec<Vec<Money>>,
+}
+
+// runs the plan for every combination of the variations, and evaluates the expression on
+// the date for each one
+pub fn sensitivity(plan: &Plan,
+                   rows: &Variation,
+                   columns: Option<&Variation>,
+                   expr: &Expr,
+                   date: NaiveDate)
+                   -> Result<Grid> {
+    let row_base = plan.parameter(&rows.rule)?;
+    let column_base = match columns {
+        Some(columns) => Some(plan.parameter(&columns.rule)?),
+        None => None,
+    };
+
+    let row_deltas = rows.deltas();
+    let column_deltas = columns.map(Variation::deltas).unwrap_or_else(|| vec![0.0]);
+
+    let mut values = Vec::new();
+    for row in &row_deltas {
+        let varied = plan.with_parameter(&rows.rule, row_base + row)?;
+        let mut result = Vec::new();
+        for column in &column_deltas {
+            let varied = match (columns, column_base) {
+                (Some(columns), Some(base)) => varied.with_parameter(&columns.rule, base + column)?,
+                _ => varied.clone(),
+            };
+            result.push(varied.evaluate_on(expr, date)?);
+        }
+        values.push(result);
+    }
+
+    Ok(Grid {
+        rows: row_deltas,
+        columns: column_deltas,
+        values: values,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_variation() {
+        let variation: Variation = "Savings: Monthly:-100:100:5".parse().unwrap();
+        assert_eq!(variation.rule, "Savings: Monthly");
+        assert_eq!(variation.deltas(), vec![-100.0, -50.0, 0.0, 50.0, 100.0]);
+    }
+}
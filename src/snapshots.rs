@@ -0,0 +1,107 @@
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind as IoErrorKind, Write};
+use std::path::PathBuf;
+use chrono::prelude::*;
+use serde_yaml;
+
+use errors::*;
+use progress::Actual;
+
+// a directory with a yaml file for every day balances were written down, named after the day.
+// snapshots are only ever added, so the past stays the way it was.
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> SnapshotStore {
+        SnapshotStore { dir: dir.into() }
+    }
+
+    fn path(&self, date: NaiveDate) -> PathBuf {
+        self.dir.join(format!("{}.yaml", date))
+    }
+
+    pub fn add(&self, snapshot: &Actual) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let mut file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(self.path(snapshot.date)) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == IoErrorKind::AlreadyExists => {
+                bail!(ErrorKind::SnapshotExists(snapshot.date))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        file.write_all(serde_yaml::to_string(snapshot)?.as_bytes())?;
+        Ok(())
+    }
+
+    // the days there are snapshots for, oldest first. anything else in the directory is left
+    // alone.
+    pub fn dates(&self) -> Result<Vec<NaiveDate>> {
+        let mut dates = Vec::new();
+        if !self.dir.exists() {
+            return Ok(dates);
+        }
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(true, |extension| extension != "yaml") {
+                continue;
+            }
+            let date = path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| NaiveDate::parse_from_str(stem, "%Y-%m-%d").ok());
+            if let Some(date) = date {
+                dates.push(date);
+            }
+        }
+        dates.sort();
+        Ok(dates)
+    }
+
+    pub fn get(&self, date: NaiveDate) -> Result<Actual> {
+        let file = match File::open(self.path(date)) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == IoErrorKind::NotFound => {
+                bail!(ErrorKind::UnknownSnapshot(date))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(serde_yaml::from_reader(file)?)
+    }
+
+    pub fn all(&self) -> Result<Vec<Actual>> {
+        let mut snapshots = Vec::new();
+        for date in self.dates()? {
+            snapshots.push(self.get(date)?);
+        }
+        Ok(snapshots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use accounts::Accounts;
+
+    #[test]
+    fn test_snapshots_are_append_only() {
+        let dir = env::temp_dir().join("financial-planner-snapshots-test");
+        let _ = fs::remove_dir_all(&dir);
+        let store = SnapshotStore::new(dir.clone());
+        let snapshot = Actual {
+            date: NaiveDate::from_ymd(2017, 3, 1),
+            accounts: Accounts::root(),
+        };
+
+        store.add(&snapshot).unwrap();
+        assert!(store.add(&snapshot).is_err());
+        assert_eq!(store.dates().unwrap(), vec![snapshot.date]);
+        assert!(store.get(NaiveDate::from_ymd(2017, 4, 1)).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
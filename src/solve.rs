@@ -0,0 +1,110 @@
+use chrono::prelude::*;
+
+use errors::*;
+use expression::Condition;
+use plan::*;
+
+pub struct Solution {
+    pub value: f64,
+    pub result: f64,
+}
+
+fn evaluate_with(plan: &Plan,
+                 rule: &str,
+                 value: f64,
+                 condition: &Condition,
+                 date: NaiveDate)
+                 -> Result<f64> {
+    Ok(plan.with_parameter(rule, value)?.evaluate_on(&condition.expr, date)?.to_f64())
+}
+
+// finds the parameter of a rule that makes the condition just true on the date, by bisecting
+// between low and high. this assumes the result only goes one way as the parameter changes.
+pub fn solve(plan: &Plan,
+             rule: &str,
+             condition: &Condition,
+             date: NaiveDate,
+             low: f64,
+             high: f64)
+             -> Result<Solution> {
+    plan.parameter(rule)?;
+
+    let gap = |value: f64| -> Result<f64> {
+        evaluate_with(plan, rule, value, condition, date).map(|result| result - condition.value)
+    };
+
+    let (mut low, mut high) = (low, high);
+    let mut low_gap = gap(low)?;
+    let high_gap = gap(high)?;
+    if low_gap * high_gap > 0.0 {
+        return Err(ErrorKind::NoSolution(low, high).into());
+    }
+
+    for _ in 0..100 {
+        if (high - low).abs() < 1e-9 * high.abs().max(1.0) {
+            break;
+        }
+        let middle = (low + high) / 2.0;
+        let middle_gap = gap(middle)?;
+        if middle_gap * low_gap > 0.0 {
+            low = middle;
+            low_gap = middle_gap;
+        } else {
+            high = middle;
+        }
+    }
+
+    // the high side of the bracket is the side where the condition holds
+    let value = if condition.holds(low_gap + condition.value) {
+        low
+    } else {
+        high
+    };
+    Ok(Solution {
+        value: value,
+        result: evaluate_with(plan, rule, value, condition, date)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan() -> Plan {
+        ::serde_yaml::from_str("start_date: 2017-01-01\n\
+                                accounts:\n  \
+                                  assets:\n    \
+                                    savings:\n      \
+                                      amount: 0\n\
+                                rules:\n  \
+                                  saving:\n    \
+                                    amount: 100\n    \
+                                    from: income\n    \
+                                    to: assets:savings\n    \
+                                    frequency: Annually\n    \
+                                    start_date: 2017-01-01")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_bisects_to_the_parameter() {
+        let condition = "assets:savings >= 10000".parse().unwrap();
+        let date = NaiveDate::from_ymd(2021, 6, 1);
+        // five savings by the date
+        let solution = solve(&plan(), "saving", &condition, date, 0.0, 10000.0).unwrap();
+        assert!((solution.value - 2000.0).abs() < 0.01);
+        assert!(solution.result >= 10000.0);
+    }
+
+    #[test]
+    fn test_no_solution_in_the_range() {
+        let condition = "assets:savings >= 10000".parse().unwrap();
+        let date = NaiveDate::from_ymd(2021, 6, 1);
+        match solve(&plan(), "saving", &condition, date, 0.0, 100.0) {
+            Err(Error(ErrorKind::NoSolution(low, high), _)) => {
+                assert_eq!((low, high), (0.0, 100.0))
+            }
+            _ => panic!("expected no solution"),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::{Accounts, Warning, Transaction};
+use gains::RealizedGain;
+use errors::*;
+use plan::{DateStream, Frequency};
+
+// a strategy is a rule that can only decide what to do with money once it can see
+// the balances of the accounts on the day it runs
+pub trait Strategy {
+    fn next_date(&self) -> Option<NaiveDate>;
+
+    // called with every transaction as it is applied, and the amount that was moved
+    fn observe(&mut self, _transaction: &Transaction, _amount: &Money) {}
+
+    // called when money is taken out of an investment account
+    fn realize(&mut self, _gain: &RealizedGain) {}
+
+    fn run(&mut self, accounts: &Accounts) -> Result<Vec<Transaction>>;
+
+    // anything worth warning about since the last time the strategy ran
+    fn warnings(&mut self) -> Vec<Warning> {
+        Vec::new()
+    }
+}
+
+// the dates a strategy runs on, this is a peekable date stream that knows when to stop
+pub struct Schedule {
+    dates: DateStream,
+    next: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+}
+
+impl Schedule {
+    pub fn new(frequency: Frequency,
+               start_date: Option<NaiveDate>,
+               end_date: Option<NaiveDate>)
+               -> Schedule {
+        let mut dates = DateStream::from((frequency, start_date));
+        let next = dates.next();
+        Schedule {
+            dates: dates,
+            next: next,
+            end_date: end_date,
+        }
+    }
+
+    pub fn peek(&self) -> Option<NaiveDate> {
+        match (self.next, self.end_date) {
+            (Some(next), Some(end_date)) if next > end_date => None,
+            (next, _) => next,
+        }
+    }
+
+    pub fn advance(&mut self) -> Option<NaiveDate> {
+        let current = self.peek();
+        self.next = self.dates.next();
+        current
+    }
+}
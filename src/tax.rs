@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+use plan::Frequency;
+use strategy::*;
+use gains::RealizedGain;
+
+// transactions tagged with these are picked up by the tax engine
+pub const INCOME: &'static str = "income";
+pub const DEDUCTIBLE: &'static str = "deductible";
+pub const WITHHELD: &'static str = "withheld";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TaxRules {
+    pub account: String,
+    pub filing_date: NaiveDate,
+    pub jurisdictions: Vec<Jurisdiction>,
+    pub capital_gains_inclusion: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Jurisdiction {
+    pub name: String,
+    pub brackets: Vec<Bracket>,
+}
+
+// the rate applies to the part of income over this bracket, up to the next bracket
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bracket {
+    pub over: Money,
+    pub rate: f64,
+}
+
+impl Jurisdiction {
+    pub fn tax(&self, income: &Money) -> Money {
+        let income = income.to_f64();
+        let mut brackets: Vec<(f64, f64)> =
+            self.brackets.iter().map(|b| (b.over.to_f64(), b.rate)).collect();
+        brackets.sort_by(|left, right| left.0.partial_cmp(&right.0).unwrap());
+
+        let mut tax = 0.0;
+        for (index, &(over, rate)) in brackets.iter().enumerate() {
+            if income <= over {
+                break;
+            }
+            let top = match brackets.get(index + 1) {
+                Some(&(next, _)) => income.min(next),
+                None => income,
+            };
+            tax += (top - over) * rate;
+        }
+        Money::from(tax)
+    }
+}
+
+impl TaxRules {
+    pub fn tax(&self, income: &Money) -> Money {
+        self.jurisdictions.iter().map(|j| j.tax(income)).sum()
+    }
+
+    pub fn taxable(&self, year: &TaxYear) -> Money {
+        let inclusion = self.capital_gains_inclusion.unwrap_or(1.0);
+        let gains = Money::from(year.capital_gains.to_f64() * inclusion);
+        (year.income.clone() + gains - year.deductions.clone()).max(Money::zero())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TaxYear {
+    pub income: Money,
+    pub deductions: Money,
+    pub withheld: Money,
+    pub capital_gains: Money,
+}
+
+impl TaxYear {
+    fn new() -> TaxYear {
+        TaxYear {
+            income: Money::zero(),
+            deductions: Money::zero(),
+            withheld: Money::zero(),
+            capital_gains: Money::zero(),
+        }
+    }
+}
+
+// files taxes for the previous calendar year every year on the filing date
+pub struct TaxStrategy {
+    schedule: Schedule,
+    rules: TaxRules,
+    years: HashMap<i32, TaxYear>,
+}
+
+impl From<TaxRules> for TaxStrategy {
+    fn from(rules: TaxRules) -> TaxStrategy {
+        TaxStrategy {
+            schedule: Schedule::new(Frequency::Annually, Some(rules.filing_date), None),
+            rules: rules,
+            years: HashMap::new(),
+        }
+    }
+}
+
+impl Strategy for TaxStrategy {
+    fn next_date(&self) -> Option<NaiveDate> {
+        self.schedule.peek()
+    }
+
+    fn observe(&mut self, transaction: &Transaction, amount: &Money) {
+        let year = self.years.entry(transaction.date.year()).or_insert_with(TaxYear::new);
+        for tag in &transaction.tags {
+            match tag.as_str() {
+                INCOME => year.income += amount.clone(),
+                DEDUCTIBLE => year.deductions += amount.clone(),
+                WITHHELD => year.withheld += amount.clone(),
+                _ => {}
+            }
+        }
+    }
+
+    fn realize(&mut self, gain: &RealizedGain) {
+        let year = self.years.entry(gain.date.year()).or_insert_with(TaxYear::new);
+        year.capital_gains += gain.amount.clone();
+    }
+
+    fn run(&mut self, _: &Accounts) -> Result<Vec<Transaction>> {
+        let date = match self.schedule.advance() {
+            Some(date) => date,
+            None => return Ok(Vec::new()),
+        };
+
+        let year = self.years.remove(&(date.year() - 1)).unwrap_or_else(TaxYear::new);
+        let owing = self.rules.tax(&self.rules.taxable(&year)) - year.withheld;
+
+        let transaction = if owing >= Money::zero() {
+            Transaction::new(Amount::Money(owing),
+                             self.rules.account.clone(),
+                             String::from("expenses:tax"),
+                             date)
+        } else {
+            Transaction::new(Amount::Money(-owing),
+                             String::from("expenses:tax"),
+                             self.rules.account.clone(),
+                             date)
+        };
+        Ok(vec![transaction])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plan::*;
+
+    fn rules() -> TaxRules {
+        TaxRules {
+            account: String::from("assets:chequing"),
+            filing_date: NaiveDate::from_ymd(2018, 4, 30),
+            jurisdictions: vec![Jurisdiction {
+                                    name: String::from("federal"),
+                                    brackets: vec![Bracket {
+                                                       over: Money::from(0),
+                                                       rate: 0.1,
+                                                   }],
+                                }],
+            capital_gains_inclusion: None,
+        }
+    }
+
+    fn tagged(amount: i32, tag: &str) -> (Transaction, Money) {
+        let transaction = Transaction::new(Amount::Money(Money::from(amount)),
+                                           String::from("income:job"),
+                                           String::from("assets:chequing"),
+                                           NaiveDate::from_ymd(2017, 6, 1))
+            .with_tags(vec![String::from(tag)]);
+        (transaction, Money::from(amount))
+    }
+
+    fn file(withheld: i32) -> Transaction {
+        let mut strategy = TaxStrategy::from(rules());
+        for (transaction, amount) in vec![tagged(50000, INCOME),
+                                          tagged(10000, DEDUCTIBLE),
+                                          tagged(withheld, WITHHELD)] {
+            strategy.observe(&transaction, &amount);
+        }
+        assert_eq!(strategy.next_date(), Some(NaiveDate::from_ymd(2018, 4, 30)));
+        let mut transactions = strategy.run(&Accounts::root()).unwrap();
+        assert_eq!(transactions.len(), 1);
+        transactions.remove(0)
+    }
+
+    #[test]
+    fn test_progressive_brackets() {
+        let federal = Jurisdiction {
+            name: String::from("federal"),
+            brackets: vec![Bracket {
+                               over: Money::from(10000),
+                               rate: 0.2,
+                           },
+                           Bracket {
+                               over: Money::from(0),
+                               rate: 0.1,
+                           }],
+        };
+        assert_eq!(federal.tax(&Money::from(5000)), Money::from(500));
+        assert_eq!(federal.tax(&Money::from(15000)), Money::from(2000));
+    }
+
+    #[test]
+    fn test_files_the_previous_year() {
+        // 10% of 40000 is 4000, and 3000 of it was withheld
+        let owing = file(3000);
+        assert_eq!(owing.eval_from_amount(&Accounts::root()).unwrap(), Money::from(1000));
+        assert_eq!(owing.from, "assets:chequing");
+        assert_eq!(owing.to, "expenses:tax");
+        assert_eq!(owing.date, NaiveDate::from_ymd(2018, 4, 30));
+    }
+
+    #[test]
+    fn test_withholding_too_much_is_refunded() {
+        let refund = file(5000);
+        assert_eq!(refund.eval_from_amount(&Accounts::root()).unwrap(), Money::from(1000));
+        assert_eq!(refund.from, "expenses:tax");
+        assert_eq!(refund.to, "assets:chequing");
+    }
+
+    #[test]
+    fn test_clipped_contribution_is_still_deductible() {
+        let plan: Plan = ::serde_yaml::from_str("start_date: 2017-01-01\n\
+                                                 accounts:\n  \
+                                                   assets:\n    \
+                                                     chequing:\n      \
+                                                       amount: 0\n    \
+                                                     rrsp:\n      \
+                                                       amount: 0\n      \
+                                                       contribution_limit:\n        \
+                                                         limits:\n          \
+                                                           - year: 2017\n            \
+                                                             amount: 5000\n\
+                                                 rules:\n  \
+                                                   salary:\n    \
+                                                     amount: 50000\n    \
+                                                     from: income:job\n    \
+                                                     to: assets:chequing\n    \
+                                                     frequency: Annually\n    \
+                                                     start_date: 2017-01-15\n    \
+                                                     tags: [income]\n  \
+                                                   rrsp:\n    \
+                                                     amount: 8000\n    \
+                                                     from: assets:chequing\n    \
+                                                     to: assets:rrsp\n    \
+                                                     frequency: Annually\n    \
+                                                     start_date: 2017-02-01\n    \
+                                                     tags: [deductible]\n\
+                                                 tax:\n  \
+                                                   account: assets:chequing\n  \
+                                                   filing_date: 2018-04-30\n  \
+                                                   jurisdictions:\n    \
+                                                     - name: federal\n      \
+                                                       brackets:\n        \
+                                                         - over: 0\n          \
+                                                           rate: 0.1")
+            .unwrap();
+        let moments = plan.moments(DateStream::once(Some(NaiveDate::from_ymd(2018, 5, 1))))
+            .unwrap();
+        // only 5000 of the contribution fits, and all of that is deducted
+        assert_eq!(moments[0].1.balance("expenses:tax").unwrap(), Money::from(4500));
+    }
+}
@@ -0,0 +1,155 @@
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+use plan::Frequency;
+use strategy::*;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Waterfall {
+    pub amount: Option<Money>,
+    pub from: String,
+    pub targets: Vec<Target>,
+    pub frequency: Frequency,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+// a target takes as much as it can until it hits one of its caps, a target without
+// any caps takes everything that is left
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Target {
+    pub account: String,
+    pub balance: Option<Money>,
+    pub per_period: Option<Money>,
+    pub per_year: Option<Money>,
+}
+
+pub struct WaterfallStrategy {
+    schedule: Schedule,
+    rule: Waterfall,
+    // how much each target has received in the current calendar year
+    contributed: Vec<(i32, Money)>,
+}
+
+impl From<Waterfall> for WaterfallStrategy {
+    fn from(rule: Waterfall) -> WaterfallStrategy {
+        WaterfallStrategy {
+            schedule: Schedule::new(rule.frequency.clone(), rule.start_date, rule.end_date),
+            contributed: vec![(0, Money::zero()); rule.targets.len()],
+            rule: rule,
+        }
+    }
+}
+
+impl WaterfallStrategy {
+    fn allocate(&mut self, date: NaiveDate, accounts: &Accounts) -> Result<Vec<Transaction>> {
+        let mut remaining = match self.rule.amount {
+            Some(ref amount) => amount.clone(),
+            None => accounts.get(&self.rule.from)?.sum(),
+        };
+
+        let mut transactions = Vec::new();
+        for (target, contributed) in self.rule.targets.iter().zip(self.contributed.iter_mut()) {
+            if remaining <= Money::zero() {
+                break;
+            }
+
+            if contributed.0 != date.year() {
+                *contributed = (date.year(), Money::zero());
+            }
+
+            let mut room = remaining.clone();
+            if let Some(ref cap) = target.balance {
+                let balance = accounts.get(&target.account)
+                    .map(Accounts::sum)
+                    .unwrap_or_else(|_| Money::zero());
+                room = room.min((cap.clone() - balance).max(Money::zero()));
+            }
+            if let Some(ref cap) = target.per_period {
+                room = room.min(cap.clone());
+            }
+            if let Some(ref cap) = target.per_year {
+                room = room.min((cap.clone() - contributed.1.clone()).max(Money::zero()));
+            }
+
+            if room > Money::zero() {
+                remaining -= room.clone();
+                contributed.1 += room.clone();
+                transactions.push(Transaction::new(Amount::Money(room),
+                                                   self.rule.from.clone(),
+                                                   target.account.clone(),
+                                                   date));
+            }
+        }
+
+        Ok(transactions)
+    }
+}
+
+impl Strategy for WaterfallStrategy {
+    fn next_date(&self) -> Option<NaiveDate> {
+        self.schedule.peek()
+    }
+
+    fn run(&mut self, accounts: &Accounts) -> Result<Vec<Transaction>> {
+        match self.schedule.advance() {
+            Some(date) => self.allocate(date, accounts),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(account: &str,
+              balance: Option<i32>,
+              per_year: Option<i32>)
+              -> Target {
+        Target {
+            account: String::from(account),
+            balance: balance.map(Money::from),
+            per_period: None,
+            per_year: per_year.map(Money::from),
+        }
+    }
+
+    fn amounts(transactions: Vec<Transaction>) -> Vec<(String, Amount)> {
+        transactions.into_iter().map(|t| (t.to, t.amount)).collect()
+    }
+
+    #[test]
+    fn test_waterfall_fills_targets_in_order() {
+        let mut accounts = Accounts::root();
+        accounts.create_account(String::from("assets:emergency"),
+                                Account::Simple(SimpleAccount::new(Money::from(9800))))
+            .unwrap();
+
+        let mut strategy = WaterfallStrategy::from(Waterfall {
+            amount: Some(Money::from(1000)),
+            from: String::from("income:paycheque"),
+            targets: vec![target("assets:emergency", Some(10000), None),
+                          target("assets:tfsa", None, Some(500)),
+                          target("assets:brokerage", None, None)],
+            frequency: Frequency::Monthly,
+            start_date: None,
+            end_date: None,
+        });
+
+        let first = strategy.allocate(NaiveDate::from_ymd(2017, 1, 1), &accounts).unwrap();
+        assert_eq!(amounts(first),
+                   vec![(String::from("assets:emergency"), Amount::Money(Money::from(200))),
+                        (String::from("assets:tfsa"), Amount::Money(Money::from(500))),
+                        (String::from("assets:brokerage"), Amount::Money(Money::from(300)))]);
+
+        // the tfsa is full for the year, but the room comes back the next year
+        let second = strategy.allocate(NaiveDate::from_ymd(2017, 2, 1), &accounts).unwrap();
+        assert_eq!(amounts(second).len(), 2);
+        let third = strategy.allocate(NaiveDate::from_ymd(2018, 1, 1), &accounts).unwrap();
+        assert_eq!(amounts(third)[1],
+                   (String::from("assets:tfsa"), Amount::Money(Money::from(500))));
+    }
+}
@@ -0,0 +1,311 @@
+use std::mem;
+use chrono::prelude::*;
+
+use money::Money;
+use accounts::*;
+use errors::*;
+use plan::Frequency;
+use strategy::*;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Withdrawal {
+    pub method: WithdrawalMethod,
+    pub accounts: Vec<String>,
+    pub to: String,
+    pub frequency: Frequency,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+// all of the amounts and rates here are yearly, they get split up over the frequency
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum WithdrawalMethod {
+    // the same amount every year, growing with inflation
+    FixedReal { amount: Money, inflation: f64 },
+    // a percentage of whatever the balance is
+    Percent { rate: f64 },
+    // guyton-klinger: start at a percentage of the balance and grow with inflation, but cut
+    // back when the withdrawal rate goes above the upper guardrail, and give yourself a raise
+    // when it goes below the lower one
+    Guardrails {
+        rate: f64,
+        inflation: f64,
+        upper: f64,
+        lower: f64,
+        adjustment: f64,
+    },
+    // required minimum withdrawals, the balance divided by the distribution period for your age
+    Minimum {
+        birth_date: NaiveDate,
+        start_age: i32,
+        divisors: Option<Vec<Divisor>>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Divisor {
+    pub age: i32,
+    pub period: f64,
+}
+
+// the irs uniform lifetime table
+const UNIFORM_LIFETIME: [(i32, f64); 49] =
+    [(72, 27.4), (73, 26.5), (74, 25.5), (75, 24.6), (76, 23.7), (77, 22.9), (78, 22.0),
+     (79, 21.1), (80, 20.2), (81, 19.4), (82, 18.5), (83, 17.7), (84, 16.8), (85, 16.0),
+     (86, 15.2), (87, 14.4), (88, 13.7), (89, 12.9), (90, 12.2), (91, 11.5), (92, 10.8),
+     (93, 10.1), (94, 9.5), (95, 8.9), (96, 8.4), (97, 7.8), (98, 7.3), (99, 6.8), (100, 6.4),
+     (101, 6.0), (102, 5.6), (103, 5.2), (104, 4.9), (105, 4.6), (106, 4.3), (107, 4.1),
+     (108, 3.9), (109, 3.7), (110, 3.5), (111, 3.4), (112, 3.3), (113, 3.1), (114, 3.0),
+     (115, 2.9), (116, 2.8), (117, 2.7), (118, 2.5), (119, 2.3), (120, 2.0)];
+
+fn distribution_period(age: i32, divisors: &Option<Vec<Divisor>>) -> f64 {
+    let table: Vec<(i32, f64)> = match *divisors {
+        Some(ref divisors) => divisors.iter().map(|d| (d.age, d.period)).collect(),
+        None => UNIFORM_LIFETIME.to_vec(),
+    };
+    // past the end of the table, the last period keeps applying
+    table.iter()
+        .filter(|&&(from, _)| from <= age)
+        .max_by_key(|&&(from, _)| from)
+        .or_else(|| table.iter().min_by_key(|&&(from, _)| from))
+        .map(|&(_, period)| period)
+        .unwrap_or(1.0)
+}
+
+pub struct WithdrawalStrategy {
+    schedule: Schedule,
+    rule: Withdrawal,
+    // the yearly amount for the year we are in
+    current: Option<(i32, f64)>,
+    exhausted: bool,
+    warnings: Vec<Warning>,
+}
+
+impl From<Withdrawal> for WithdrawalStrategy {
+    fn from(rule: Withdrawal) -> WithdrawalStrategy {
+        WithdrawalStrategy {
+            schedule: Schedule::new(rule.frequency.clone(), rule.start_date, rule.end_date),
+            rule: rule,
+            current: None,
+            exhausted: false,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl WithdrawalStrategy {
+    fn yearly(&mut self, year: i32, balance: f64) -> f64 {
+        let previous = self.current;
+        if let Some((current, amount)) = previous {
+            if current == year {
+                return amount;
+            }
+        }
+
+        let yearly = match self.rule.method {
+            WithdrawalMethod::FixedReal { ref amount, inflation } => {
+                match previous {
+                    Some((last_year, last)) => last * (1.0 + inflation).powi(year - last_year),
+                    None => amount.to_f64(),
+                }
+            }
+            WithdrawalMethod::Percent { rate } => balance * rate,
+            WithdrawalMethod::Guardrails { rate, inflation, upper, lower, adjustment } => {
+                match previous {
+                    Some((last_year, last)) => {
+                        let next = last * (1.0 + inflation).powi(year - last_year);
+                        let current_rate = if balance > 0.0 {
+                            next / balance
+                        } else {
+                            rate
+                        };
+                        if current_rate > rate * (1.0 + upper) {
+                            next * (1.0 - adjustment)
+                        } else if current_rate < rate * (1.0 - lower) {
+                            next * (1.0 + adjustment)
+                        } else {
+                            next
+                        }
+                    }
+                    None => balance * rate,
+                }
+            }
+            WithdrawalMethod::Minimum { birth_date, start_age, ref divisors } => {
+                let age = year - birth_date.year();
+                if age < start_age {
+                    0.0
+                } else {
+                    balance / distribution_period(age, divisors)
+                }
+            }
+        };
+
+        self.current = Some((year, yearly));
+        yearly
+    }
+}
+
+impl Strategy for WithdrawalStrategy {
+    fn next_date(&self) -> Option<NaiveDate> {
+        self.schedule.peek()
+    }
+
+    fn run(&mut self, accounts: &Accounts) -> Result<Vec<Transaction>> {
+        let date = match self.schedule.advance() {
+            Some(date) => date,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut balances = Vec::new();
+        for account in &self.rule.accounts {
+            balances.push((account.clone(), accounts.get(account)?.sum().max(Money::zero())));
+        }
+        let total = balances.iter().map(|&(_, ref balance)| balance.to_f64()).sum::<f64>();
+
+        // a percentage of the balance is recalculated every time, everything else is a
+        // yearly amount
+        let periods = self.rule.frequency.periods_per_year();
+        let percent = match self.rule.method {
+            WithdrawalMethod::Percent { rate } => Some(rate),
+            _ => None,
+        };
+        let wanted = match percent {
+            Some(rate) => total * rate / periods,
+            None => self.yearly(date.year(), total) / periods,
+        };
+
+        let mut needed = Money::from(wanted);
+        let mut transactions = Vec::new();
+        for (account, balance) in balances {
+            if needed <= Money::zero() {
+                break;
+            }
+            let amount = balance.min(needed.clone());
+            if amount > Money::zero() {
+                needed -= amount.clone();
+                transactions.push(Transaction::new(Amount::Money(amount),
+                                                   account,
+                                                   self.rule.to.clone(),
+                                                   date));
+            }
+        }
+
+        // anything less than half a cent is just rounding
+        if needed.to_f64() >= 0.005 && !self.exhausted {
+            self.exhausted = true;
+            let message = format!("funds exhausted, withdrawal was {} short", needed);
+            self.warnings.push(Warning::new(date, self.rule.to.clone(), message));
+        }
+
+        Ok(transactions)
+    }
+
+    fn warnings(&mut self) -> Vec<Warning> {
+        mem::replace(&mut self.warnings, Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accounts(rrsp: i32, tfsa: i32) -> Accounts {
+        let mut accounts = Accounts::root();
+        for &(path, amount) in &[("assets:rrsp", rrsp), ("assets:tfsa", tfsa)] {
+            accounts.create_account(String::from(path),
+                                    Account::Simple(SimpleAccount::new(Money::from(amount))))
+                .unwrap();
+        }
+        accounts
+    }
+
+    fn strategy(method: WithdrawalMethod) -> WithdrawalStrategy {
+        WithdrawalStrategy::from(Withdrawal {
+            method: method,
+            accounts: vec![String::from("assets:rrsp"), String::from("assets:tfsa")],
+            to: String::from("assets:chequing"),
+            frequency: Frequency::Annually,
+            start_date: Some(NaiveDate::from_ymd(2030, 1, 1)),
+            end_date: None,
+        })
+    }
+
+    // what came out of every account, in cents
+    fn withdrawn(strategy: &mut WithdrawalStrategy, accounts: &Accounts) -> Vec<(String, i64)> {
+        strategy.run(accounts)
+            .unwrap()
+            .iter()
+            .map(|t| {
+                let amount = t.eval_from_amount(accounts).unwrap().to_f64();
+                (t.from.clone(), (amount * 100.0).round() as i64)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fixed_real_grows_with_inflation() {
+        let mut strategy = strategy(WithdrawalMethod::FixedReal {
+            amount: Money::from(40000),
+            inflation: 0.02,
+        });
+        let accounts = accounts(100000, 50000);
+        assert_eq!(withdrawn(&mut strategy, &accounts),
+                   vec![(String::from("assets:rrsp"), 4000000)]);
+        assert_eq!(withdrawn(&mut strategy, &accounts),
+                   vec![(String::from("assets:rrsp"), 4080000)]);
+    }
+
+    #[test]
+    fn test_percent_of_the_balance() {
+        let mut strategy = strategy(WithdrawalMethod::Percent { rate: 0.04 });
+        assert_eq!(withdrawn(&mut strategy, &accounts(100000, 50000)),
+                   vec![(String::from("assets:rrsp"), 600000)]);
+        assert_eq!(withdrawn(&mut strategy, &accounts(50000, 0)),
+                   vec![(String::from("assets:rrsp"), 200000)]);
+    }
+
+    #[test]
+    fn test_guardrails_cut_back_after_a_fall() {
+        let mut strategy = strategy(WithdrawalMethod::Guardrails {
+            rate: 0.05,
+            inflation: 0.0,
+            upper: 0.2,
+            lower: 0.2,
+            adjustment: 0.1,
+        });
+        assert_eq!(withdrawn(&mut strategy, &accounts(100000, 50000)),
+                   vec![(String::from("assets:rrsp"), 750000)]);
+        // 7500 would be 7.5% of what is left, over the 6% guardrail
+        assert_eq!(withdrawn(&mut strategy, &accounts(100000, 0)),
+                   vec![(String::from("assets:rrsp"), 675000)]);
+    }
+
+    #[test]
+    fn test_minimum_starts_at_the_start_age() {
+        let mut strategy = strategy(WithdrawalMethod::Minimum {
+            birth_date: NaiveDate::from_ymd(1958, 1, 1),
+            start_age: 73,
+            divisors: None,
+        });
+        let accounts = accounts(100000, 50000);
+        // 72 in 2030, and 73 in 2031 with a period of 26.5
+        assert_eq!(withdrawn(&mut strategy, &accounts), vec![]);
+        assert_eq!(withdrawn(&mut strategy, &accounts),
+                   vec![(String::from("assets:rrsp"), 566038)]);
+    }
+
+    #[test]
+    fn test_running_out_warns_once() {
+        let mut strategy = strategy(WithdrawalMethod::FixedReal {
+            amount: Money::from(200000),
+            inflation: 0.0,
+        });
+        let accounts = accounts(100000, 50000);
+        assert_eq!(withdrawn(&mut strategy, &accounts),
+                   vec![(String::from("assets:rrsp"), 10000000),
+                        (String::from("assets:tfsa"), 5000000)]);
+        assert_eq!(strategy.warnings().len(), 1);
+        withdrawn(&mut strategy, &accounts);
+        assert!(strategy.warnings().is_empty());
+    }
+}